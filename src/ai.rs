@@ -0,0 +1,277 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::Mutex;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::game::{GameError, GameState};
+use crate::house_rules::HouseRules;
+use crate::rules::Rules;
+use crate::scoring::{calculate_scores, Combinations, PlayerData};
+use crate::solver::Solver;
+
+/// How an AI player plays its turn
+pub trait Strategy {
+    /// Which dice to hold before the next reroll, `rolls_left` being at least 1.
+    /// Holding all dice means the AI is happy with the roll.
+    fn hold(&self, player: &PlayerData, dice: &[u8], rolls_left: u32) -> Vec<bool>;
+    /// Which combination to score the final dice in
+    fn choose(&self, player: &PlayerData, dice: &[u8]) -> usize;
+}
+
+/// Makes the moves of a turn on a game, directly or through something that
+/// also records them or sends them to a host
+pub trait Moves {
+    fn game(&self) -> &GameState;
+    fn hold(&mut self, index: usize, held: bool) -> Result<(), GameError>;
+    fn reroll(&mut self) -> Result<(), GameError>;
+    fn score(&mut self, combination: usize) -> Result<u8, GameError>;
+}
+
+impl Moves for GameState {
+    fn game(&self) -> &GameState {
+        self
+    }
+    fn hold(&mut self, index: usize, held: bool) -> Result<(), GameError> {
+        GameState::hold(self, index, held);
+        Ok(())
+    }
+    fn reroll(&mut self) -> Result<(), GameError> {
+        GameState::reroll(self)
+    }
+    fn score(&mut self, combination: usize) -> Result<u8, GameError> {
+        GameState::score(self, combination)
+    }
+}
+
+fn usable_combinations(player: &PlayerData, dice: &[u8]) -> Vec<usize> {
+    (0..player.combinations_used.len()).filter(|&i| player.can_use(i, dice)).collect()
+}
+
+/// Rerolls and scores at random
+pub struct RandomAi {
+    rng: Mutex<ChaCha8Rng>,
+}
+
+impl RandomAi {
+    /// Makes the same moves in the same situations for the same seed
+    pub fn new(seed: u64) -> Self {
+        RandomAi { rng: Mutex::new(ChaCha8Rng::seed_from_u64(seed)) }
+    }
+}
+
+impl Strategy for RandomAi {
+    fn hold(&self, _player: &PlayerData, dice: &[u8], _rolls_left: u32) -> Vec<bool> {
+        let mut rng = self.rng.lock().unwrap();
+        (0..dice.len()).map(|_| rng.gen_bool(0.5)).collect()
+    }
+    fn choose(&self, player: &PlayerData, dice: &[u8]) -> usize {
+        let combinations_left = usable_combinations(player, dice);
+        combinations_left[self.rng.lock().unwrap().gen_range(0..combinations_left.len())]
+    }
+}
+
+/// Never rerolls and takes the combination scoring the most right now
+pub struct GreedyAi;
+
+impl Strategy for GreedyAi {
+    fn hold(&self, _player: &PlayerData, dice: &[u8], _rolls_left: u32) -> Vec<bool> {
+        vec![true; dice.len()]
+    }
+    fn choose(&self, player: &PlayerData, dice: &[u8]) -> usize {
+        let scores = calculate_scores(dice, player);
+        let points = |i: usize| player.rules.column_weight(player.rules.column(i)) * scores[i] as i32;
+        *usable_combinations(player, dice).iter().max_by_key(|&&i| points(i))
+            .expect("AI must have at least one combination to choose")
+    }
+}
+
+/// Holds dice by a few rules of thumb and scores greedily
+pub struct HeuristicAi;
+
+impl Strategy for HeuristicAi {
+    /// Keeps a finished roll worth a lot, chases an open straight,
+    /// otherwise keeps the most common (and highest) face
+    fn hold(&self, player: &PlayerData, dice: &[u8], _rolls_left: u32) -> Vec<bool> {
+        use Combinations::*;
+
+        let scores = calculate_scores(dice, player);
+        // In any column, combinations the rules don't have are never free
+        let free = |c: Combinations| player.rules.indices(c).any(|i| player.can_use(i, dice));
+        let made = |c: Combinations| player.rules.indices(c).any(|i| player.can_use(i, dice) && scores[i] > 0);
+
+        if made(Yahtzee) || made(FullStraight) || made(LargeStraight) || made(FullHouse) ||
+            (made(SmallStraight) && !free(LargeStraight)) {
+            return vec![true; dice.len()];
+        }
+
+        let mut counts = [0u8; 7];
+        for &d in dice {
+            counts[d as usize] += 1;
+        }
+
+        if free(LargeStraight) || free(SmallStraight) {
+            let run = [[3, 4, 5, 6], [2, 3, 4, 5], [1, 2, 3, 4]].into_iter()
+                .find(|run| run.iter().all(|&f| counts[f] > 0));
+            if let Some(run) = run {
+                let mut held = vec![false; dice.len()];
+                let mut to_hold = run.to_vec();
+                for (i, d) in dice.iter().enumerate() {
+                    if let Some(pos) = to_hold.iter().position(|&f| f == *d as usize) {
+                        to_hold.remove(pos);
+                        held[i] = true;
+                    }
+                }
+                return held;
+            }
+        }
+
+        let face = (1..=6).max_by_key(|&f| (counts[f], f)).unwrap();
+        let mut held = vec![false; dice.len()];
+        if counts[face] >= 2 {
+            for (i, &d) in dice.iter().enumerate() {
+                held[i] = d as usize == face;
+            }
+        }
+        held
+    }
+    fn choose(&self, player: &PlayerData, dice: &[u8]) -> usize {
+        GreedyAi.choose(player, dice)
+    }
+}
+
+/// Maximizes the expected final score
+pub struct OptimalAi {
+    pub solver: Solver,
+}
+
+impl Strategy for OptimalAi {
+    fn hold(&self, player: &PlayerData, dice: &[u8], rolls_left: u32) -> Vec<bool> {
+        self.solver.best_hold(player, dice, rolls_left)
+    }
+    fn choose(&self, player: &PlayerData, dice: &[u8]) -> usize {
+        self.solver.best_combination(player, dice)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Random,
+    Greedy,
+    Heuristic,
+    Optimal,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [Difficulty::Random, Difficulty::Greedy, Difficulty::Heuristic, Difficulty::Optimal];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Random => "random",
+            Difficulty::Greedy => "greedy",
+            Difficulty::Heuristic => "heuristic",
+            Difficulty::Optimal => "optimal",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| d.name() == name)
+    }
+    pub fn description(&self) -> &'static str {
+        match self {
+            Difficulty::Random => "rerolls and scores at random",
+            Difficulty::Greedy => "never rerolls, takes the most points",
+            Difficulty::Heuristic => "holds dice by rules of thumb",
+            Difficulty::Optimal => "maximizes its expected score",
+        }
+    }
+
+    /// Creates the AI for the difficulty playing by `rules`. The optimal AI loads its
+    /// strategy from `strategy_cache`, solving the game (which takes a while) if it's missing.
+    /// The random AI plays by `seed`, the others don't need one. Rules the
+    /// optimal AI can't play (see [`Rules::has_solver`]) and house rules get the
+    /// heuristic AI instead.
+    pub fn strategy(&self, rules: Rules, house_rules: &HouseRules, strategy_cache: &Path, seed: u64)
+        -> Box<dyn Strategy + Send + Sync> {
+        match self {
+            Difficulty::Random => Box::new(RandomAi::new(seed)),
+            Difficulty::Greedy => Box::new(GreedyAi),
+            Difficulty::Optimal if rules.has_solver() && house_rules.is_empty() => {
+                Box::new(OptimalAi { solver: Solver::load_or_compute(strategy_cache, rules) })
+            }
+            Difficulty::Heuristic | Difficulty::Optimal => Box::new(HeuristicAi),
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Plays the whole turn of the current player, returning the chosen combination
+pub fn play_turn(strategy: &dyn Strategy, game_state: &mut GameState) -> usize {
+    play_turn_with(strategy, game_state, |_| ())
+}
+
+/// [`play_turn`] through `moves`, calling `on_roll` with them after each roll
+/// of the turn, the one it starts with included
+pub fn play_turn_with<M: Moves + ?Sized>(strategy: &dyn Strategy, moves: &mut M, mut on_roll: impl FnMut(&mut M)) -> usize {
+    loop {
+        on_roll(moves);
+        let game_state = moves.game();
+        if game_state.rolls_left() == 0 {
+            break;
+        }
+        let held = strategy.hold(&game_state.current_player().data, game_state.dice(), game_state.rolls_left());
+        if held.iter().all(|&h| h) {
+            break;
+        }
+        for (i, &h) in held.iter().enumerate() {
+            moves.hold(i, h).expect("the dice can be held until the last roll");
+        }
+        moves.reroll().expect("rolls are left");
+    }
+    let game_state = moves.game();
+    let choice = strategy.choose(&game_state.current_player().data, game_state.dice());
+    moves.score(choice).expect("AI chooses a usable combination");
+    choice
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn holds_finished_roll() {
+        assert_eq!(HeuristicAi.hold(&PlayerData::default(), &[2, 3, 1, 4, 5], 2), [true; 5]);
+        assert_eq!(HeuristicAi.hold(&PlayerData::default(), &[2, 2, 6, 6, 6], 2), [true; 5]);
+    }
+
+    #[test]
+    fn chases_straight() {
+        assert_eq!(HeuristicAi.hold(&PlayerData::default(), &[2, 3, 3, 4, 5], 2), [true, true, false, true, true]);
+    }
+
+    #[test]
+    fn holds_most_common_face() {
+        assert_eq!(HeuristicAi.hold(&PlayerData::default(), &[6, 1, 1, 6, 2], 2), [true, false, false, true, false]);
+        assert_eq!(HeuristicAi.hold(&PlayerData::default(), &[6, 5, 3, 2, 1], 2), [false; 5]);
+    }
+
+    #[test]
+    fn every_strategy_finishes_a_game() {
+        let strategies: [&dyn Strategy; 3] = [&RandomAi::new(0), &GreedyAi, &HeuristicAi];
+
+        for (strategy, rules) in strategies.into_iter().flat_map(|s| Rules::ALL.map(|r| (s, r))) {
+            let mut game_state = GameState::with_rules(GameState::default().players, rules, 0);
+            while !game_state.is_over() {
+                play_turn(strategy, &mut game_state);
+            }
+            assert!(game_state.players.iter().all(|p| p.data.is_complete()));
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Grades a player's decisions in a recorded game against the optimal strategy,
+//! the way chess players check their games with an engine.
+//!
+//! Every hold before a reroll and every scored combination is a decision. Its
+//! value is the expected final score after it, so the loss of a decision is how
+//! many points it gave away on average compared to the best choice.
+
+use crate::replay::{Replay, ReplayEvent};
+use crate::solver::Solver;
+
+/// Decisions losing less than this are as good as the best one, the rest is float noise
+const TOLERANCE: f32 = 0.01;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Choice {
+    /// The dice held for a reroll
+    Hold(Vec<bool>),
+    Score(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct Decision {
+    pub turn: u32,
+    pub dice: Vec<u8>,
+    /// Rerolls that were left when deciding
+    pub rolls_left: u32,
+    pub choice: Choice,
+    pub best: Choice,
+    /// Expected final score after the choice
+    pub expected: f32,
+    /// Expected final score after the best choice
+    pub best_expected: f32,
+}
+
+impl Decision {
+    /// Expected points lost by not making the best choice
+    pub fn loss(&self) -> f32 {
+        (self.best_expected - self.expected).max(0.0)
+    }
+    pub fn is_best(&self) -> bool {
+        self.loss() < TOLERANCE
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    /// The seat of the analysed player
+    pub player: usize,
+    pub decisions: Vec<Decision>,
+}
+
+impl Analysis {
+    /// Goes through the decisions of the player in seat `player`
+    pub fn of(replay: &Replay, solver: &Solver, player: usize) -> Self {
+        let frames = replay.frames();
+        let mut decisions = Vec::new();
+
+        for (before, frame) in frames.iter().zip(&frames[1..]) {
+            let game_state = &before.game_state;
+            let choice = match &frame.event {
+                ReplayEvent::Hold { player: p, held } if *p == player => Choice::Hold(held.clone()),
+                ReplayEvent::Score { player: p, combination, .. } if *p == player => Choice::Score(*combination),
+                _ => continue,
+            };
+            let data = &game_state.players[player].data;
+            let dice = game_state.dice().to_vec();
+            let rolls_left = game_state.rolls_left();
+            let hint = solver.hint(data, &dice, rolls_left);
+
+            let expected = match &choice {
+                Choice::Hold(held) => data.score as f32 + solver.hold_value(data, &dice, held, rolls_left),
+                Choice::Score(combination) => hint.combinations[*combination].unwrap_or(0.0),
+            };
+            // Scoring with rerolls left is also a choice not to reroll
+            let best_score = hint.combinations.iter()
+                .enumerate()
+                .filter_map(|(i, v)| v.map(|v| (Choice::Score(i), v)))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            let (best, best_expected) = match (hint.hold, best_score) {
+                (Some((held, value)), Some((_, score_value))) if value > score_value + TOLERANCE => (Choice::Hold(held), value),
+                (Some((held, value)), None) => (Choice::Hold(held), value),
+                (_, Some(best_score)) => best_score,
+                (None, None) => continue,
+            };
+
+            decisions.push(Decision { turn: game_state.turn.n, dice, rolls_left, choice, best, expected, best_expected });
+        }
+        Analysis { player, decisions }
+    }
+
+    /// Expected points lost over the whole game
+    pub fn loss(&self) -> f32 {
+        self.decisions.iter().map(Decision::loss).sum()
+    }
+
+    /// Percentage of the decisions that were the best ones
+    pub fn accuracy(&self) -> f32 {
+        if self.decisions.is_empty() {
+            return 100.0;
+        }
+        let best = self.decisions.iter().filter(|d| d.is_best()).count();
+        100.0 * best as f32 / self.decisions.len() as f32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ai::Difficulty;
+    use crate::game::{GameState, Player, PlayerKind, Turn};
+    use crate::replay::Recorder;
+    use crate::rules::Rules;
+    use crate::scoring::Combinations;
+
+    /// The last turn of a game with only Chance left
+    fn last_turn(dice: [u8; 5]) -> GameState {
+        let mut player = Player::new("A", PlayerKind::Human);
+        for i in 0..Combinations::Chance as usize {
+            player.data.combinations_used[i] = true;
+        }
+        GameState::restore(Turn { player: 0, n: 13 }, vec![player], dice.to_vec(), vec![false; 5], 2, 7)
+    }
+
+    fn analyse(game_state: &mut GameState, moves: impl FnOnce(&mut Recorder<Vec<u8>>, &mut GameState)) -> Analysis {
+        let mut recorder = Recorder::new(Vec::new(), game_state, Difficulty::Optimal);
+        moves(&mut recorder, game_state);
+        let replay = Replay::read(&recorder.into_inner().unwrap()[..]).unwrap();
+        Analysis::of(&replay, &Solver::compute(Rules::Yahtzee, 12), 0)
+    }
+
+    #[test]
+    fn rerolling_sixes_for_chance_is_a_mistake() {
+        let mut game_state = last_turn([6, 6, 6, 1, 1]);
+        let analysis = analyse(&mut game_state, |recorder, game_state| {
+            recorder.reroll(game_state).unwrap();
+            recorder.score(game_state, Combinations::Chance as usize).unwrap();
+        });
+
+        let [hold, score] = &analysis.decisions[..] else { panic!("two decisions") };
+        assert_eq!(hold.choice, Choice::Hold(vec![false; 5]));
+        assert_eq!(hold.best, Choice::Hold(vec![true, true, true, false, false]));
+        assert!(hold.loss() > 1.0);
+        assert_eq!(hold.turn, 13);
+        assert_eq!((score.choice.clone(), score.rolls_left), (Choice::Score(Combinations::Chance as usize), 1));
+        assert!(analysis.accuracy() <= 50.0);
+        assert!((analysis.loss() - hold.loss() - score.loss()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn scoring_with_rerolls_left_can_be_a_mistake() {
+        let mut game_state = last_turn([6, 6, 6, 1, 1]);
+        let analysis = analyse(&mut game_state, |recorder, game_state| {
+            recorder.score(game_state, Combinations::Chance as usize).unwrap();
+        });
+        let [score] = &analysis.decisions[..] else { panic!("one decision") };
+        assert_eq!(score.expected, 20.0);
+        assert_eq!(score.best, Choice::Hold(vec![true, true, true, false, false]));
+        assert_eq!(analysis.accuracy(), 0.0);
+    }
+
+    #[test]
+    fn keeping_a_yahtzee_is_best() {
+        let mut game_state = last_turn([5; 5]);
+        let analysis = analyse(&mut game_state, |recorder, game_state| {
+            recorder.score(game_state, Combinations::Chance as usize).unwrap();
+        });
+        let [score] = &analysis.decisions[..] else { panic!("one decision") };
+        assert!(score.is_best());
+        assert_eq!(score.expected, 25.0);
+        assert_eq!(analysis.accuracy(), 100.0);
+    }
+}
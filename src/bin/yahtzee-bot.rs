@@ -0,0 +1,65 @@
+//! Reference bot for the bot protocol, see `src/bot.rs`.
+//!
+//! It keeps rerolling for the face it has most of, unless the dice already
+//! score well somewhere, and then takes the box that scores the most.
+//!
+//! ```console
+//! $ yahtzee --bot-protocol --opponents 2
+//! ```
+//! runs a game whose first seat is played over stdin and stdout, which is
+//! where a harness connects this bot.
+
+use std::io::{self, BufRead, Write};
+
+use yahtzee::net::{ClientMessage, GameView, ServerMessage};
+
+/// Points that end the turn right away
+const GOOD_ENOUGH: u8 = 25;
+
+fn send(message: &ClientMessage) {
+    let line = serde_json::to_string(message).expect("messages serialize") + "\n";
+    let mut stdout = io::stdout();
+    if stdout.write_all(line.as_bytes()).and_then(|_| stdout.flush()).is_err() {
+        std::process::exit(1);
+    }
+}
+
+/// The move for a state of the bot's turn
+fn play(view: &GameView) -> ClientMessage {
+    let best = view.available.iter().copied()
+        .max_by_key(|&i| (view.scores[i], i))
+        .expect("a turn has a box to score");
+    if view.rolls_left == 0 || view.scores[best] >= GOOD_ENOUGH {
+        return ClientMessage::Score { combination: best };
+    }
+
+    let count = |face: u8| view.dice.iter().filter(|&&d| d == face).count();
+    let face = (1..=6).max_by_key(|&face| (count(face), face)).expect("there are faces");
+    let held: Vec<bool> = view.dice.iter().map(|&d| d == face).collect();
+    if held != view.held {
+        ClientMessage::Hold { held }
+    } else {
+        ClientMessage::Reroll
+    }
+}
+
+fn main() {
+    let mut seat = None;
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        match serde_json::from_str(&line) {
+            Ok(ServerMessage::Welcome { seat: s, .. }) => seat = Some(s),
+            Ok(ServerMessage::State(view)) if view.over => break,
+            Ok(ServerMessage::State(view)) if Some(view.turn.player) == seat => send(&play(&view)),
+            Ok(ServerMessage::Error { message, .. }) => {
+                eprintln!("The game rejected a move: {}", message);
+                std::process::exit(1);
+            }
+            Ok(_) => (),
+            Err(e) => {
+                eprintln!("Couldn't read the game's message: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Bots: programs that play a seat of the game over their stdin and stdout.
+//!
+//! The bot protocol is the [network protocol](crate::net) without joining.
+//! The game sends the bot a `welcome` with its seat first. Whenever it's the
+//! bot's turn the game sends it the `state`, and the bot answers every state
+//! with one `hold`, `reroll` or `score` message:
+//!
+//! ```text
+//! game: {"type":"welcome","seat":0,"difficulty":"heuristic"}
+//! game: {"type":"state","turn":{"player":0,"n":1},"dice":[3,1,6,6,2],"held":[false,false,false,false,false],"rolls_left":2, ...}
+//! bot:  {"type":"hold","held":[false,false,true,true,false]}
+//! game: {"type":"state", ... "held":[false,false,true,true,false],"rolls_left":2, ...}
+//! bot:  {"type":"reroll"}
+//! game: {"type":"state", ... "rolls_left":1, ...}
+//! bot:  {"type":"score","combination":5}
+//! ```
+//!
+//! A move the game rejects is answered with an `error` instead, and the bot
+//! tries another move. Scoring ends the turn, so the game sends nothing until
+//! the bot's next turn. Once the game is over every bot gets the final state,
+//! with `"over":true`, and the game closes its stdin.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use crate::ai::{play_turn, Difficulty, Strategy};
+use crate::game::{GameError, GameState};
+use crate::net::{ClientMessage, GameView, ServerMessage};
+
+/// Rejected moves in a row after which a bot is given up on
+pub const MAX_ERRORS: u32 = 20;
+
+/// The game's end of the connection to a bot
+pub struct Bot<R, W> {
+    reader: R,
+    writer: W,
+    process: Option<Child>,
+}
+
+/// A bot running as a child process
+pub type BotProcess = Bot<BufReader<ChildStdout>, ChildStdin>;
+
+impl BotProcess {
+    /// Starts the bot program, which plays over its stdin and stdout
+    pub fn spawn(command: &mut Command) -> io::Result<Self> {
+        let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        let reader = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let writer = child.stdin.take().expect("stdin is piped");
+        Ok(Bot { reader, writer, process: Some(child) })
+    }
+}
+
+impl<R, W> Drop for Bot<R, W> {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.process {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Bot<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Bot { reader, writer, process: None }
+    }
+
+    fn send(&mut self, message: &ServerMessage) -> io::Result<()> {
+        let line = serde_json::to_string(message).expect("messages serialize") + "\n";
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.flush()
+    }
+
+    fn error(&mut self, message: &str, error: Option<GameError>) -> io::Result<()> {
+        self.send(&ServerMessage::Error { message: message.to_string(), error })
+    }
+
+    /// Tells the bot which seat it plays
+    pub fn welcome(&mut self, seat: usize, difficulty: Difficulty) -> io::Result<()> {
+        self.send(&ServerMessage::Welcome { seat, difficulty })
+    }
+
+    /// Plays the current player's turn by the bot's moves, returning the chosen combination
+    pub fn play_turn(&mut self, game_state: &mut GameState) -> io::Result<usize> {
+        let mut errors = 0;
+        self.send(&ServerMessage::State(GameView::of(game_state)))?;
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the bot quit during its turn"));
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            let result = match serde_json::from_str(&line) {
+                Ok(ClientMessage::Hold { held }) if held.len() != game_state.dice().len() => {
+                    Err((format!("hold needs {} dice, not {}", game_state.dice().len(), held.len()), None))
+                }
+                Ok(ClientMessage::Hold { held }) => {
+                    for (i, &h) in held.iter().enumerate() {
+                        game_state.hold(i, h);
+                    }
+                    Ok(())
+                }
+                Ok(ClientMessage::Reroll) => game_state.reroll().map_err(|e| (e.to_string(), Some(e))),
+                Ok(ClientMessage::Score { combination }) => match game_state.score(combination) {
+                    Ok(_) => return Ok(combination),
+                    Err(e) => Err((e.to_string(), Some(e))),
+                },
+                Ok(ClientMessage::Join { .. } | ClientMessage::Hint) => {
+                    Err(("bots can only hold, reroll and score".to_string(), None))
+                }
+                Err(e) => Err((format!("invalid message: {}", e), None)),
+            };
+            match result {
+                Ok(()) => {
+                    errors = 0;
+                    self.send(&ServerMessage::State(GameView::of(game_state)))?;
+                }
+                Err((message, error)) => {
+                    errors += 1;
+                    if errors == MAX_ERRORS {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                                  format!("the bot made {} invalid moves in a row", MAX_ERRORS)));
+                    }
+                    self.error(&message, error)?;
+                }
+            }
+        }
+    }
+
+    /// Sends the bot the final state
+    pub fn game_over(&mut self, game_state: &GameState) -> io::Result<()> {
+        self.send(&ServerMessage::State(GameView::of(game_state)))
+    }
+}
+
+/// Plays the game to the end, the seats in `bots` by their bots and all the
+/// others by `strategy`, which plays at `difficulty`
+pub fn play_game<R: BufRead, W: Write>(game_state: &mut GameState, bots: &mut [(usize, Bot<R, W>)],
+                                       difficulty: Difficulty, strategy: &dyn Strategy) -> io::Result<()> {
+    for (seat, bot) in bots.iter_mut() {
+        bot.welcome(*seat, difficulty)?;
+    }
+    while !game_state.is_over() {
+        let seat = game_state.turn.player;
+        match bots.iter_mut().find(|(s, _)| *s == seat) {
+            Some((_, bot)) => bot.play_turn(game_state)?,
+            None => play_turn(strategy, game_state),
+        };
+    }
+    for (_, bot) in bots.iter_mut() {
+        bot.game_over(game_state)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::{Player, PlayerKind};
+
+    fn messages(output: &[u8]) -> Vec<ServerMessage> {
+        output.split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn answers_every_move() {
+        let mut game_state = GameState::with_seed(vec![Player::new("Bot", PlayerKind::AI)], 3);
+        let moves = "{\"type\":\"hold\",\"held\":[true,true,false,false,false]}\n\
+                     {\"type\":\"hold\",\"held\":[true]}\n\
+                     {\"type\":\"roll\"}\n\
+                     {\"type\":\"reroll\"}\n\
+                     {\"type\":\"score\",\"combination\":12}\n";
+        let mut output = Vec::new();
+        let choice = Bot::new(moves.as_bytes(), &mut output).play_turn(&mut game_state).unwrap();
+
+        assert_eq!(choice, 12);
+        assert!(game_state.players[0].data.has_used(12));
+        let messages = messages(&output);
+        assert_eq!(messages.len(), 5);
+        assert!(matches!(&messages[1], ServerMessage::State(view) if view.held[..2] == [true, true]));
+        assert!(matches!(&messages[2], ServerMessage::Error { message, .. } if message == "hold needs 5 dice, not 1"));
+        assert!(matches!(&messages[3], ServerMessage::Error { error: None, .. }));
+        assert!(matches!(&messages[4], ServerMessage::State(view) if view.rolls_left == 1 && view.available.len() == 13));
+    }
+
+    #[test]
+    fn gives_up_on_broken_bots() {
+        let mut game_state = GameState::default();
+        let moves = "{\"type\":\"score\",\"combination\":99}\n".repeat(MAX_ERRORS as usize);
+        let result = Bot::new(moves.as_bytes(), io::sink()).play_turn(&mut game_state);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let result = Bot::new(&b""[..], io::sink()).play_turn(&mut game_state);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
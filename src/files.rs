@@ -0,0 +1,55 @@
+//! Reading and writing the game's files without losing them to a crash or a
+//! newer version of the game.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+
+/// Reads a JSON file whose `version` is at most `newest`, the version this
+/// game writes. Newer files are left alone rather than read wrong.
+pub fn load_versioned<T: DeserializeOwned>(path: &Path, newest: u32, version: impl FnOnce(&T) -> u32) -> io::Result<T> {
+    let value: T = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    let version = version(&value);
+    if version > newest {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+            format!("version {} is from a newer version of the game", version)));
+    }
+    Ok(value)
+}
+
+/// Writes a file with `write`, replacing the old one at `path` only once the
+/// new one is on the disk
+pub fn write_atomically(path: &Path, write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    write(&mut writer)?;
+    writer.into_inner()?.sync_all()?;
+    std::fs::rename(tmp_path, path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn replaces_the_file() {
+        let dir = std::env::temp_dir().join(format!("yahtzee-files-test-{}", std::process::id()));
+        let path = dir.join("file.json");
+        write_atomically(&path, |writer| writer.write_all(b"old")).unwrap();
+        let failed = write_atomically(&path, |writer| {
+            writer.write_all(b"half")?;
+            Err(io::Error::other("crash"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+        write_atomically(&path, |writer| writer.write_all(b"new")).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
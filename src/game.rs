@@ -0,0 +1,356 @@
+use std::fmt;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::house_rules::HouseRules;
+use crate::rules::Rules;
+use crate::scoring::{calculate_scores, PlayerData};
+
+pub const MAX_PLAYERS: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayerKind {
+    Human,
+    AI
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Player {
+    pub name: String,
+    pub kind: PlayerKind,
+    pub data: PlayerData,
+    /// Turns in which the player looked at a hint
+    #[serde(default)]
+    pub hints: u32,
+    /// Rerolls left over from earlier turns, by rules that save them
+    #[serde(default)]
+    pub saved_rolls: u32,
+}
+
+impl Player {
+    pub fn new(name: &str, kind: PlayerKind) -> Self {
+        Player { name: name.to_string(), kind, data: PlayerData::default(), hints: 0, saved_rolls: 0 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Turn {
+    /// Index of the player whose turn it is
+    pub player: usize,
+    pub n: u32
+}
+
+impl Turn {
+    pub fn next(&mut self, players: usize) {
+        self.player += 1;
+        if self.player == players {
+            self.player = 0;
+            self.n += 1;
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameError {
+    GameOver,
+    NoRollsLeft,
+    CombinationUnavailable(usize),
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::GameOver => write!(f, "the game is over"),
+            GameError::NoRollsLeft => write!(f, "no rolls left this turn"),
+            GameError::CombinationUnavailable(i) => write!(f, "combination {} can't be used", i),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+pub fn randomize_dice(rng: &mut impl Rng, dice: &mut [u8], to_randomize: &[usize]) {
+    for &i in to_randomize {
+        dice[i] = rng.gen_range(1..=6);
+    }
+}
+
+/// The whole game: the players with their scorecards, whose turn it is and
+/// the dice of that turn.
+///
+/// Every turn starts with a fresh roll of all dice, five or as many as the
+/// rules have. The current player can hold dice and reroll the rest up to two
+/// times, or as often as the house rules say, plus the rerolls they saved if
+/// the rules allow it, and the turn ends once they score a combination.
+///
+/// All dice come from a generator seeded with [`seed`](Self::seed), so the same
+/// seed and the same moves always make the same game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameState {
+    pub turn: Turn,
+    pub players: Vec<Player>,
+    dice: Vec<u8>,
+    held: Vec<bool>,
+    rolls_left: u32,
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+/// A human against an AI
+impl Default for GameState {
+    fn default() -> Self {
+        Self::new(vec![Player::new("You", PlayerKind::Human), Player::new("AI", PlayerKind::AI)])
+    }
+}
+
+impl GameState {
+    /// Starts a game with a random seed, the players take turns in the given order
+    pub fn new(players: Vec<Player>) -> Self {
+        Self::with_seed(players, rand::thread_rng().gen())
+    }
+
+    /// Starts a game whose dice are determined by `seed`
+    pub fn with_seed(players: Vec<Player>, seed: u64) -> Self {
+        Self::with_rules(players, Rules::default(), seed)
+    }
+
+    /// Starts a game played by `rules`, giving every player an empty scorecard for them
+    pub fn with_rules(players: Vec<Player>, rules: Rules, seed: u64) -> Self {
+        Self::with_house_rules(players, rules, HouseRules::default(), seed)
+    }
+
+    /// Starts a game played by `rules` as changed by `house_rules`
+    pub fn with_house_rules(mut players: Vec<Player>, rules: Rules, house_rules: HouseRules, seed: u64) -> Self {
+        assert!(!players.is_empty() && players.len() <= MAX_PLAYERS, "a game needs 1 to {} players", MAX_PLAYERS);
+        for player in &mut players {
+            player.data = PlayerData::with_house_rules(rules, house_rules.clone());
+            player.saved_rolls = 0;
+        }
+        let mut game_state = GameState {
+            turn: Turn { player: 0, n: 1 },
+            players,
+            dice: vec![0; rules.dice()],
+            held: vec![false; rules.dice()],
+            rolls_left: 0,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        };
+        game_state.roll();
+        game_state
+    }
+
+    /// Puts together a game in progress, e.g. one received over the network.
+    /// Its later dice restart from `seed` rather than continuing the original game.
+    pub fn restore(turn: Turn, players: Vec<Player>, dice: Vec<u8>, held: Vec<bool>, rolls_left: u32, seed: u64) -> Self {
+        GameState { turn, players, dice, held, rolls_left, seed, rng: ChaCha8Rng::seed_from_u64(seed) }
+    }
+
+    pub fn dice(&self) -> &[u8] {
+        &self.dice
+    }
+    pub fn held(&self) -> &[bool] {
+        &self.held
+    }
+    pub fn rolls_left(&self) -> u32 {
+        self.rolls_left
+    }
+    pub fn is_over(&self) -> bool {
+        self.turn.n > self.rules().turns()
+    }
+    /// The rules of the players' scorecards
+    pub fn rules(&self) -> Rules {
+        self.players[0].data.rules
+    }
+    pub fn house_rules(&self) -> &HouseRules {
+        &self.players[0].data.house_rules
+    }
+    /// Whether the optimal AI knows the game, which it doesn't with house rules
+    pub fn has_solver(&self) -> bool {
+        self.rules().has_solver() && self.house_rules().is_empty()
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn current_player(&self) -> &Player {
+        &self.players[self.turn.player]
+    }
+
+    /// Players from the highest score to the lowest, each with their place.
+    /// Players with equal scores share a place.
+    pub fn standings(&self) -> Vec<(usize, &Player)> {
+        let mut players: Vec<&Player> = self.players.iter().collect();
+        players.sort_by_key(|p| std::cmp::Reverse(p.data.score));
+        let mut standings: Vec<(usize, &Player)> = Vec::with_capacity(players.len());
+        for (i, player) in players.into_iter().enumerate() {
+            let place = match standings.last() {
+                Some(&(place, last)) if last.data.score == player.data.score => place,
+                _ => i + 1,
+            };
+            standings.push((place, player));
+        }
+        standings
+    }
+
+    /// Scores of the current dice for the current player
+    pub fn scores(&self) -> Vec<u8> {
+        calculate_scores(&self.dice, &self.current_player().data)
+    }
+    pub fn can_score(&self, index: usize) -> bool {
+        self.current_player().data.can_use(index, &self.dice)
+    }
+
+    /// Rolls all dice, starting the current turn over with the rerolls the
+    /// current player saved
+    pub fn roll(&mut self) {
+        let all: Vec<usize> = (0..self.dice.len()).collect();
+        randomize_dice(&mut self.rng, &mut self.dice, &all);
+        self.held.fill(false);
+        let player = self.turn.player;
+        self.rolls_left = self.house_rules().rerolls() + std::mem::take(&mut self.players[player].saved_rolls);
+    }
+
+    pub fn hold(&mut self, index: usize, held: bool) {
+        self.held[index] = held;
+    }
+    pub fn toggle_hold(&mut self, index: usize) {
+        self.held[index] = !self.held[index];
+    }
+
+    /// Records that the current player looked at a hint this turn
+    pub fn use_hint(&mut self) {
+        let player = self.turn.player;
+        self.players[player].hints += 1;
+    }
+
+    pub fn reroll(&mut self) -> Result<(), GameError> {
+        if self.is_over() {
+            return Err(GameError::GameOver);
+        }
+        if self.rolls_left == 0 {
+            return Err(GameError::NoRollsLeft);
+        }
+        let to_randomize: Vec<_> = (0..self.dice.len()).filter(|&i| !self.held[i]).collect();
+        randomize_dice(&mut self.rng, &mut self.dice, &to_randomize);
+
+        self.rolls_left -= 1;
+        Ok(())
+    }
+
+    /// Scores the current dice in the given combination, then passes the turn
+    /// on and rolls for the next player. Returns the points scored.
+    pub fn score(&mut self, index: usize) -> Result<u8, GameError> {
+        if self.is_over() {
+            return Err(GameError::GameOver);
+        }
+        if index >= self.rules().scorecard_len() || !self.can_score(index) {
+            return Err(GameError::CombinationUnavailable(index));
+        }
+        let points = self.scores()[index];
+        let player = &mut self.players[self.turn.player];
+        player.data.add_score(index, &self.dice);
+        if player.data.rules.saves_rolls() {
+            player.saved_rolls += self.rolls_left;
+        }
+        self.turn.next(self.players.len());
+        if !self.is_over() {
+            self.roll();
+        }
+        Ok(points)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reroll_limit() {
+        let mut game_state = GameState::default();
+        assert_eq!(game_state.rolls_left(), 2);
+        game_state.hold(0, true);
+        let held_die = game_state.dice()[0];
+        assert!(game_state.reroll().is_ok());
+        assert!(game_state.reroll().is_ok());
+        assert_eq!(game_state.reroll(), Err(GameError::NoRollsLeft));
+        assert_eq!(game_state.dice()[0], held_die);
+    }
+
+    #[test]
+    fn score_passes_turn() {
+        let mut game_state = GameState::default();
+        game_state.score(12).unwrap();
+        assert_eq!(game_state.turn.player, 1);
+        assert!(game_state.players[0].data.has_used(12));
+        assert_eq!(game_state.score(13), Err(GameError::CombinationUnavailable(13)));
+        assert_eq!(game_state.rolls_left(), 2);
+    }
+
+    #[test]
+    fn full_game() {
+        let players = ["Alice", "Bob", "Carol"].iter()
+            .map(|name| Player::new(name, PlayerKind::Human))
+            .collect();
+        let mut game_state = GameState::new(players);
+        for i in 0..13 {
+            game_state.score(i).unwrap();
+            // A joker may force the others into a different box
+            for _ in 0..2 {
+                let choice = (0..13).find(|&j| game_state.can_score(j)).unwrap();
+                game_state.score(choice).unwrap();
+            }
+        }
+        assert!(game_state.is_over());
+        assert!(game_state.players.iter().all(|p| p.data.is_complete()));
+        assert_eq!(game_state.score(0), Err(GameError::GameOver));
+    }
+
+    #[test]
+    fn standings_share_places() {
+        let mut game_state = GameState::new(["A", "B", "C", "D"].iter()
+            .map(|name| Player::new(name, PlayerKind::AI))
+            .collect());
+        for (player, score) in game_state.players.iter_mut().zip([10, 30, 10, 5]) {
+            player.data.score = score;
+        }
+        let standings: Vec<_> = game_state.standings().iter().map(|(place, p)| (*place, p.name.as_str())).collect();
+        assert_eq!(standings, [(1, "B"), (2, "A"), (2, "C"), (4, "D")]);
+    }
+    #[test]
+    fn maxi_saves_rolls() {
+        let players = vec![Player::new("A", PlayerKind::Human), Player::new("B", PlayerKind::Human)];
+        let mut game_state = GameState::with_rules(players, Rules::MaxiYatzy, 1);
+        assert_eq!((game_state.dice().len(), game_state.held().len()), (6, 6));
+
+        // A scores right away, B uses one reroll
+        game_state.score(0).unwrap();
+        assert_eq!(game_state.players[0].saved_rolls, 2);
+        game_state.reroll().unwrap();
+        game_state.score(0).unwrap();
+        assert_eq!(game_state.players[1].saved_rolls, 1);
+
+        // A's next turn has their saved rerolls on top
+        assert_eq!(game_state.rolls_left(), 4);
+        assert_eq!(game_state.players[0].saved_rolls, 0);
+        game_state.score(1).unwrap();
+        assert_eq!(game_state.rolls_left(), 3);
+    }
+
+    #[test]
+    fn house_rules_rerolls() {
+        let house_rules = HouseRules { rerolls: Some(3), ..Default::default() };
+        let mut game_state = GameState::with_house_rules(vec![Player::new("A", PlayerKind::Human)], Rules::Yahtzee, house_rules, 1);
+        assert_eq!(game_state.rolls_left(), 3);
+        for _ in 0..3 {
+            game_state.reroll().unwrap();
+        }
+        assert!(game_state.reroll().is_err());
+        game_state.score(0).unwrap();
+        assert_eq!(game_state.rolls_left(), 3);
+        assert_eq!(game_state.house_rules().rerolls, Some(3));
+    }
+}
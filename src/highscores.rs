@@ -0,0 +1,269 @@
+//! The highscore table, kept as versioned JSON.
+//!
+//! Older versions of the game wrote `name: score` or `name: score: difficulty`
+//! lines to a text file, [`Highscores::load_or_migrate`] reads those once.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::Difficulty;
+use crate::files::{load_versioned, write_atomically};
+use crate::game::Player;
+use crate::rules::Rules;
+use crate::scoring::PlayerData;
+
+/// Version of the highscore file written by this version of the game
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Highscore {
+    pub name: String,
+    pub score: i32,
+    /// The details are missing from scores migrated from the old text file
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(default)]
+    pub difficulty: Option<Difficulty>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// The points of every combination and the bonuses
+    #[serde(default)]
+    pub scorecard: Option<PlayerData>,
+    /// Turns in which the player looked at a hint
+    #[serde(default)]
+    pub hints: u32,
+    /// Scores from before there were other rules are Yahtzee ones
+    #[serde(default)]
+    pub rules: Rules,
+}
+
+impl Highscore {
+    /// The score of a finished game, dated today
+    pub fn new(player: &Player, difficulty: Difficulty, seed: u64) -> Self {
+        Highscore {
+            name: player.name.clone(),
+            score: player.data.score,
+            date: Some(today()),
+            difficulty: Some(difficulty),
+            seed: Some(seed),
+            scorecard: Some(player.data.clone()),
+            hints: player.hints,
+            rules: player.data.rules,
+        }
+    }
+}
+
+impl fmt::Display for Highscore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.score)?;
+        let mut details: Vec<String> = self.difficulty.iter().map(|d| d.to_string())
+            .chain(self.date.clone())
+            .collect();
+        if self.hints > 0 {
+            details.push("with hints".to_string());
+        }
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Today's date in UTC as YYYY-MM-DD
+fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64 / 86400;
+    // Howard Hinnant's days to civil date algorithm
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Highscores from the best to the worst
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Highscores {
+    pub version: u32,
+    pub highscores: Vec<Highscore>,
+}
+
+impl Default for Highscores {
+    /// The scores to beat in a new table
+    fn default() -> Self {
+        let developer = |name: &str, score| Highscore {
+            name: name.to_string(),
+            score,
+            date: None,
+            difficulty: None,
+            seed: None,
+            scorecard: None,
+            hints: 0,
+            rules: Rules::Yahtzee,
+        };
+        Highscores { version: VERSION, highscores: vec![developer("Developer", 205), developer("Vladimir", 120)] }
+    }
+}
+
+impl Highscores {
+    /// Reads the highscores from `path`. If that file doesn't exist yet, they're
+    /// taken from the old text file at `old_path` or a new table is started.
+    pub fn load_or_migrate(path: &Path, old_path: Option<&Path>) -> io::Result<Self> {
+        if path.exists() {
+            return Self::load(path);
+        }
+        match old_path {
+            Some(old_path) if old_path.exists() => Self::read_old(BufReader::new(File::open(old_path)?)),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        load_versioned(path, VERSION, |highscores: &Highscores| highscores.version)
+    }
+
+    /// Reads the old `name: score` and `name: score: difficulty` lines, names may contain colons
+    pub fn read_old(reader: impl BufRead) -> io::Result<Self> {
+        let mut highscores = Highscores { version: VERSION, highscores: Vec::new() };
+        for line in reader.lines() {
+            let line = line?;
+            let with_difficulty = line.rsplitn(3, ':').collect::<Vec<_>>();
+            let (name, score, difficulty) = match with_difficulty[..] {
+                [difficulty, score, name] if Difficulty::from_name(difficulty.trim()).is_some() => {
+                    (name, score, Difficulty::from_name(difficulty.trim()))
+                }
+                _ => match line.rsplit_once(':') {
+                    Some((name, score)) => (name, score, None),
+                    None => continue,
+                },
+            };
+            if let Ok(score) = score.trim().parse() {
+                highscores.add(Highscore {
+                    name: name.to_string(),
+                    score,
+                    date: None,
+                    difficulty,
+                    seed: None,
+                    scorecard: None,
+                    hints: 0,
+                    rules: Rules::Yahtzee,
+                });
+            }
+        }
+        Ok(highscores)
+    }
+
+    /// Writes the highscores to `path`, readable by people too
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_atomically(path, |writer| Ok(serde_json::to_writer_pretty(writer, self)?))
+    }
+
+    /// The scores of games played by the rules, from the best
+    pub fn of(&self, rules: Rules) -> impl Iterator<Item = &Highscore> {
+        self.highscores.iter().filter(move |h| h.rules == rules)
+    }
+
+    pub fn best(&self, rules: Rules) -> Option<&Highscore> {
+        self.of(rules).next()
+    }
+
+    /// Adds a score after the equal and better ones, returning its place from 0
+    pub fn add(&mut self, highscore: Highscore) -> usize {
+        let pos = self.highscores.partition_point(|h| h.score >= highscore.score);
+        self.highscores.insert(pos, highscore);
+        pos
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::PlayerKind;
+
+    #[test]
+    fn migrates_old_file() {
+        let old = "Developer: 205\nVladimir: 120\nme:myself: 150: optimal\nbroken line\nx: y\nSomeone: 180: heuristic\n";
+        let highscores = Highscores::read_old(old.as_bytes()).unwrap();
+        let scores: Vec<_> = highscores.highscores.iter()
+            .map(|h| (h.name.as_str(), h.score, h.difficulty))
+            .collect();
+        assert_eq!(scores, [
+            ("Developer", 205, None),
+            ("Someone", 180, Some(Difficulty::Heuristic)),
+            ("me:myself", 150, Some(Difficulty::Optimal)),
+            ("Vladimir", 120, None),
+        ]);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut highscores = Highscores::default();
+        let mut player = Player::new("Name: with a colon", PlayerKind::Human);
+        player.data.add_score(12, &[6, 6, 6, 6, 5]);
+        assert_eq!(highscores.add(Highscore::new(&player, Difficulty::Greedy, 42)), 2);
+
+        let dir = std::env::temp_dir().join(format!("yahtzee-highscores-test-{}", std::process::id()));
+        let path = dir.join("highscores.json");
+        highscores.save(&path).unwrap();
+        let loaded = Highscores::load_or_migrate(&path, Some(&dir.join("missing.txt"))).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let h = &loaded.highscores[2];
+        assert_eq!((h.name.as_str(), h.score, h.seed), ("Name: with a colon", 29, Some(42)));
+        assert_eq!(h.scorecard.as_ref().unwrap().combinations_scores[12], 29);
+        assert_eq!(h.date.as_ref().unwrap().len(), 10);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let dir = std::env::temp_dir().join(format!("yahtzee-highscores-version-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("highscores.json");
+        std::fs::write(&path, r#"{"version":99,"highscores":[]}"#).unwrap();
+        let result = Highscores::load(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn hinted_scores_are_flagged() {
+        let mut player = Player::new("Me", PlayerKind::Human);
+        player.data.score = 180;
+        let mut highscore = Highscore::new(&player, Difficulty::Optimal, 0);
+        highscore.date = None;
+        assert_eq!(highscore.to_string(), "Me: 180 (optimal)");
+        player.hints = 2;
+        let highscore = Highscore { date: None, ..Highscore::new(&player, Difficulty::Optimal, 0) };
+        assert_eq!(highscore.to_string(), "Me: 180 (optimal, with hints)");
+    }
+
+    #[test]
+    fn tables_are_kept_per_ruleset() {
+        let mut highscores = Highscores::default();
+        let mut player = Player::new("Me", PlayerKind::Human);
+        player.data = PlayerData::new(Rules::Yatzy);
+        player.data.score = 100;
+        assert_eq!(highscores.add(Highscore::new(&player, Difficulty::Heuristic, 0)), 2);
+        assert_eq!(highscores.best(Rules::Yahtzee).unwrap().name, "Developer");
+        assert_eq!(highscores.best(Rules::Yatzy).unwrap().name, "Me");
+        assert_eq!(highscores.of(Rules::Yatzy).count(), 1);
+    }
+
+    #[test]
+    fn ties_keep_the_older_score_first() {
+        let mut highscores = Highscores::default();
+        let mut player = Player::new("New", PlayerKind::Human);
+        player.data.score = 205;
+        assert_eq!(highscores.add(Highscore::new(&player, Difficulty::Random, 0)), 1);
+    }
+}
@@ -0,0 +1,140 @@
+//! House rules: a group's own values for the fixed points of the rules,
+//! read from `rules.toml` in the config directory.
+//!
+//! ```toml
+//! rerolls = 3
+//! full_house = 30
+//! small_straight = 25
+//! large_straight = 35
+//! yahtzee = 50
+//! yahtzee_bonus = false
+//! upper_threshold = 70
+//! upper_bonus = 50
+//! ```
+//!
+//! Every value is optional and the ones left out keep those of the rules. The
+//! combination values replace whatever the combination scores by any ruleset,
+//! e.g. the total of the dice for a Yatzy Full House, and are also what a
+//! Joker scores there. The optimal AI only knows the standard values, so
+//! games with house rules are played without it.
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rules::Rules;
+use crate::scoring::Combinations;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HouseRules {
+    /// Rerolls per turn, instead of 2
+    pub rerolls: Option<u32>,
+    pub full_house: Option<u8>,
+    pub small_straight: Option<u8>,
+    pub large_straight: Option<u8>,
+    pub yahtzee: Option<u8>,
+    /// Whether extra Yahtzees score the 100 point bonus, by rules with Jokers
+    pub yahtzee_bonus: Option<bool>,
+    /// Upper section sum that earns the bonus
+    pub upper_threshold: Option<i32>,
+    pub upper_bonus: Option<u8>,
+}
+
+impl HouseRules {
+    /// Reads the house rules, which are the standard ones if there's no file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)?;
+        toml::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.message().to_string()))
+    }
+
+    /// Whether the rules are played as they are
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn rerolls(&self) -> u32 {
+        self.rerolls.unwrap_or(2)
+    }
+
+    pub fn yahtzee_bonus(&self) -> bool {
+        self.yahtzee_bonus.unwrap_or(true)
+    }
+
+    pub fn upper_threshold(&self, rules: Rules) -> i32 {
+        self.upper_threshold.unwrap_or_else(|| rules.upper_threshold())
+    }
+
+    pub fn upper_bonus(&self, rules: Rules) -> u8 {
+        self.upper_bonus.unwrap_or_else(|| rules.upper_bonus())
+    }
+
+    /// What a combination scoring `points` by the rules is worth with the house
+    /// rules. Combinations that aren't made stay at 0.
+    pub fn points(&self, combination: Combinations, points: u8) -> u8 {
+        let value = match combination {
+            Combinations::FullHouse => self.full_house,
+            Combinations::SmallStraight => self.small_straight,
+            Combinations::LargeStraight => self.large_straight,
+            Combinations::Yahtzee => self.yahtzee,
+            _ => None,
+        };
+        match value {
+            Some(value) if points > 0 => value,
+            _ => points,
+        }
+    }
+}
+
+/// One line per value that differs from the rules
+impl fmt::Display for HouseRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(rerolls) = self.rerolls {
+            writeln!(f, "Rerolls per turn: {}", rerolls)?;
+        }
+        for (name, value) in [("Full House", self.full_house), ("Small Straight", self.small_straight),
+                              ("Large Straight", self.large_straight), ("Yahtzee", self.yahtzee)] {
+            if let Some(value) = value {
+                writeln!(f, "{}: {} points", name, value)?;
+            }
+        }
+        if let Some(bonus) = self.yahtzee_bonus {
+            writeln!(f, "Yahtzee bonus: {}", if bonus { "on" } else { "off" })?;
+        }
+        if let Some(threshold) = self.upper_threshold {
+            writeln!(f, "Upper bonus threshold: {}", threshold)?;
+        }
+        if let Some(bonus) = self.upper_bonus {
+            writeln!(f, "Upper bonus: {} points", bonus)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_toml() {
+        let house_rules: HouseRules = toml::from_str("rerolls = 3\nfull_house = 30\nyahtzee_bonus = false\n").unwrap();
+        assert_eq!(house_rules.rerolls(), 3);
+        assert_eq!(house_rules.points(Combinations::FullHouse, 25), 30);
+        assert_eq!(house_rules.points(Combinations::FullHouse, 0), 0);
+        assert!(!house_rules.yahtzee_bonus());
+        assert_eq!(house_rules.upper_threshold(Rules::Yahtzee), 63);
+        assert_eq!(house_rules.to_string(), "Rerolls per turn: 3\nFull House: 30 points\nYahtzee bonus: off\n");
+        assert!(toml::from_str::<HouseRules>("fullhouse = 30").is_err());
+    }
+
+    #[test]
+    fn missing_file_is_no_house_rules() {
+        let path = std::env::temp_dir().join(format!("yahtzee-no-house-rules-{}", std::process::id()));
+        assert!(HouseRules::load(&path).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+//! Where the turn screens draw, worked out from the size of the terminal each
+//! time they're drawn so that they follow it when it's resized.
+//!
+//! The dice are on the left and the scorecard on the right. On terminals too
+//! small for that, below about 80x24, the scorecard gets compact: one column
+//! of points per column of the scorecard, and no blank lines. The compact
+//! scorecard goes next to the dice if it fits and under them otherwise. If
+//! it doesn't fit either way, the screens only ask for a bigger terminal.
+
+use yahtzee::Rules;
+
+/// Rows of the turn and the scores at the top of the screen
+pub const HEADER: i32 = 2;
+
+/// Rows from the message above the dice to the last row of the hints below them
+const DICE_ROWS: i32 = 7;
+
+/// Width of each column of points in a scorecard with several columns
+pub const CELL: i32 = 6;
+
+/// Columns from the left edge to the end of the Hold button
+pub fn dice_width(rules: Rules) -> i32 {
+    1 + rules.dice() as i32 * 4 + 14
+}
+
+/// Width of the names on the scorecard
+fn name_width(rules: Rules) -> i32 {
+    (0..rules.combinations().len())
+        .map(|i| rules.combination_name(i).len() as i32)
+        .chain(["Yahtzee bonus".len() as i32])
+        .max()
+        .expect("there are combinations")
+}
+
+/// Label of the upper bonus row, shortened on the compact scorecard
+pub fn bonus_label(threshold: i32, compact: bool) -> String {
+    if compact {
+        format!("Bonus ({}+)", threshold)
+    } else {
+        format!("Bonus ({} in total or more)", threshold)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub height: i32,
+    pub width: i32,
+    pub compact: bool,
+    /// Row of the message above the dice, like the rolls left
+    pub message_y: i32,
+    /// Row of the dice and the buttons, held dice are a row above it
+    pub dice_y: i32,
+    /// Row of the scorecard's header, or of its first box if it has none
+    pub card_y: i32,
+    /// Column of the scorecard's names
+    pub card_x: i32,
+    /// Right edge of the scorecard's points
+    pub card_right: i32,
+}
+
+impl Layout {
+    /// Rows and columns the scorecard of the rules needs
+    pub fn card_size(rules: Rules, compact: bool) -> (i32, i32) {
+        let combinations = rules.combinations().len() as i32;
+        let columns = rules.columns() as i32;
+        // The Yahtzee bonus, and the totals of each column
+        let extra = if columns > 1 { 2 } else { rules.has_jokers() as i32 };
+        // House rules can raise the threshold up to three digits
+        let bonus = bonus_label(999, compact).len() as i32;
+        match (compact, columns > 1) {
+            // Header, upper total, bonus and a blank line
+            (false, false) => (combinations + 4 + extra, (name_width(rules) + 21).max(bonus + 6)),
+            (false, true) => (combinations + 4 + extra, (name_width(rules) + 10).max(bonus + 1) + columns * CELL),
+            (true, false) => (combinations + 2 + extra, name_width(rules).max(bonus) + 6),
+            (true, true) => (combinations + 3 + extra, name_width(rules).max(bonus) + 1 + columns * CELL),
+        }
+    }
+
+    /// The layout for the terminal, `None` if it's too small. `footer` is the
+    /// width of the controls of the screen on the last row, 0 if it has none.
+    /// The dice stay above them, the scorecard only if it would overlap them.
+    pub fn new(size: (i32, i32), rules: Rules, footer: i32) -> Option<Layout> {
+        let (height, width) = size;
+        let bottom = height - (footer > 0) as i32;
+        let card_bottom = |card_x: i32| if card_x > footer { height } else { bottom };
+        let dice_width = dice_width(rules);
+
+        let (rows, card_width) = Self::card_size(rules, false);
+        let card_right = width - 2;
+        let card_x = (width / 2).min(card_right - card_width);
+        let dice_y = height / 2;
+        if card_x > dice_width && HEADER + rows <= card_bottom(card_x) && dice_y - 3 >= HEADER && dice_y + 4 <= bottom {
+            let card_y = (dice_y - 10).min(card_bottom(card_x) - rows).max(HEADER);
+            return Some(Layout { height, width, compact: false, message_y: dice_y - 3, dice_y, card_y, card_x, card_right });
+        }
+
+        let (rows, card_width) = Self::card_size(rules, true);
+        let compact = |card_y, card_x| Layout {
+            height, width, compact: true,
+            message_y: HEADER,
+            dice_y: HEADER + 3,
+            card_y,
+            card_x,
+            card_right: card_x + card_width,
+        };
+        // Next to the dice, or under them
+        if dice_width + 1 + card_width < width && HEADER + rows <= card_bottom(dice_width + 1) && HEADER + DICE_ROWS <= bottom {
+            return Some(compact(HEADER, dice_width + 1));
+        }
+        if dice_width.max(1 + card_width) < width && HEADER + DICE_ROWS + rows <= card_bottom(1) {
+            return Some(compact(HEADER + DICE_ROWS, 1));
+        }
+        None
+    }
+}
@@ -0,0 +1,55 @@
+//! Yahtzee game engine without any UI.
+//!
+//! [`GameState`] runs a game between any mix of human and AI players: it rolls the
+//! dice, lets the current player hold and reroll them, and scores the chosen
+//! combination with [`calculate_scores`]. The ncurses game in `main.rs` is
+//! just one frontend for it.
+//!
+//! ```
+//! use yahtzee::{play_turn, GameState, HeuristicAi};
+//!
+//! let mut game = GameState::default();
+//! while !game.is_over() {
+//!     play_turn(&HeuristicAi, &mut game);
+//! }
+//! for (place, player) in game.standings() {
+//!     println!("{}. {}: {}", place, player.name, player.data.score);
+//! }
+//! ```
+
+pub mod ai;
+pub mod analysis;
+pub mod bot;
+pub mod files;
+pub mod game;
+pub mod highscores;
+pub mod house_rules;
+pub mod net;
+pub mod paths;
+pub mod ratings;
+pub mod replay;
+pub mod rules;
+pub mod save;
+pub mod scoring;
+pub mod simulate;
+pub mod solver;
+pub mod stats;
+pub mod tournament;
+
+pub use ai::{play_turn, play_turn_with, Difficulty, GreedyAi, HeuristicAi, Moves, OptimalAi, RandomAi, Strategy};
+pub use analysis::{Analysis, Choice, Decision};
+pub use bot::{Bot, BotProcess};
+pub use game::{GameError, GameState, Player, PlayerKind, Turn, MAX_PLAYERS};
+pub use highscores::{Highscore, Highscores};
+pub use house_rules::HouseRules;
+pub use net::{Client, ClientMessage, ServerMessage};
+pub use paths::Paths;
+pub use replay::{Frame, Recorder, Replay, ReplayEvent};
+pub use ratings::{Rating, Ratings};
+pub use rules::Rules;
+pub use save::SavedGame;
+pub use scoring::{calculate_scores, is_yahtzee, score_combination, score_index_to_string, Combinations, PlayerData};
+pub use simulate::{simulate, AiResults, Simulation};
+pub use solver::{Hint, Solver};
+pub use stats::{PlayerStats, Stats};
+pub use tournament::{Contestant, Entrant, Format, Tournament};
@@ -1,21 +1,24 @@
-use std::fmt;
-use std::io::Write;
-use std::time::Duration;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
 
 use keys::*;
+use layout::{bonus_label, Layout, CELL};
 use ncurses::*;
+use table::{add_highscores, box_name, dice_string, load_highscores, record_stats, replay_path, roll_progress,
+            LocalTable, Table};
 
 use rand::Rng;
+use yahtzee::*;
+
+mod layout;
+mod plain;
+mod table;
 
 const REGULAR_PAIR: i16 = 0;
 const HIGLIGHT_PAIR: i16 = 1;
 
-const HELP_LINES: [&str; 20] = ["                       Yatzee rules.", 
-    "",
-    "On each turn every player rolls 5 dice.", 
-    "They can save any dice they want and reroll the dice up to 2 times.",
-    "Once the dice are rolled, the player crosses out one of the 13 combinations to get points",
-    "The combinations: ",
+/// The help after its first lines, which depend on the rules
+const HELP_LINES: [&str; 26] = ["The combinations: ",
     "    First six 'upper section' combinations score total of the respective cards",
     "    For example, [1, 2, 3, 3, 3] scores 1 for Aces, 2 for Twos and 3 * 3 = 9 for Threes",
     "'Lower section' combinations:",
@@ -24,94 +27,25 @@ const HELP_LINES: [&str; 20] = ["                       Yatzee rules.",
     "    Small Straight - 4 consecutive numbers. 30 points.",
     "    Large Straight - 5 consecutive numbers. 40 points.",
     "    Full House - 3 dice of one number and 2 of another. 25 points.",
+    "    Yahtzee - 5 dice of the same number. 50 points.",
     "    Chance - no requirements. The score is the total of all dice.",
     "",
-    "Once the players cross out all 13 combinations,",
-    "the game ends and the player with the most points wind the game",
+    "Every extra Yahtzee after scoring 50 for Yahtzee earns a 100 point bonus.",
+    "An extra Yahtzee is a Joker: it must fill its upper box if that is free,",
+    "otherwise it scores any lower combination in full (e.g. 40 for Large Straight).",
+    "",
+    "Once the players cross out all their combinations,",
+    "the game ends and the player with the most points wins the game",
+    "",
+    "Yatzy, the Scandinavian rules, adds One Pair and Two Pairs. The kinds score the matching dice,",
+    "Full House the total, straights 1-5 and 2-6 score 15 and 20, the bonus is 50, there are no Jokers.",
+    "Triple Yahtzee has three columns whose totals count x1, x2 and x3, and lasts 39 turns.",
+    "Maxi Yatzy rolls 6 dice and adds Three Pairs, 5 of a kind, Full Straight (1-6), Castle (two triples)",
+    "and Tower (4 and 2 of a kind). Rerolls you don't use are saved for your later turns.",
     "",
-    "                           Press Enter to play"
+    "                         Press Enter to go back"
 ];
 
-enum PlayerKind {
-    Human,
-    AI
-}
-
-struct Turn {
-    player: PlayerKind,
-    n: u32
-}
-
-impl Default for Turn {
-    fn default() -> Self {
-        Turn {
-            player: PlayerKind::Human,
-            n: 1
-        }
-    }
-}
-
-impl Turn {
-    fn next(&mut self) {
-        match self.player {
-            PlayerKind::Human => self.player = PlayerKind::AI,
-            PlayerKind::AI => {
-                self.player = PlayerKind::Human;
-                self.n += 1;
-            }
-        }
-    }
-
-}
-
-impl fmt::Display for Turn {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.player {
-            PlayerKind::Human => {
-                write!(f, "Your turn ({})", self.n)
-            }
-            PlayerKind::AI => {
-                write!(f, "AI turn ({})", self.n)
-            }
-        }
-    }
-}
-
-#[derive(Default, Debug)]
-struct PlayerData {
-    score: i32,
-    combinations_scores: [u8; 13],
-    combinations_used: [bool; 13],
-    got_upper_bonus: bool,
-}
-
-impl PlayerData {
-    fn has_used(&self, index: usize) -> bool {
-        self.combinations_used[index]
-    }
-    fn upper_sum(&self) -> i32 {
-        self.combinations_scores[0..6].iter().sum::<u8>() as i32
-    }
-    fn add_score(&mut self, index: usize, score: u8) {
-        self.combinations_scores[index] = score;
-        self.combinations_used[index] = true;
-        self.score += score as i32;
-        if !self.got_upper_bonus &&
-            index <= Combinations::Sixes as usize && 
-            self.upper_sum() >= 63 {
-            self.score += 35 as i32;
-            self.got_upper_bonus = true;
-        }
-    }
-}
-
-#[derive(Default)]
-struct GameState {
-    turn: Turn,
-    player: PlayerData,
-    ai: PlayerData
-}
-
 fn get_win_size(win: *mut i8) -> (i32, i32) {
     let mut x = 0; 
     let mut y = 0;
@@ -132,133 +66,95 @@ fn print_centered_left_align(win: *mut i8, lines: &[&str]) {
 
 fn print_centered(win: *mut i8, s: &str) {
     let (win_height, win_width) = get_win_size(win);
-    mvaddstr(win_height / 2, (win_width - s.len() as i32) / 2, s);
+    mvaddstr(win_height / 2, ((win_width - s.len() as i32) / 2).max(0), s);
 }
 
-fn help(win: *mut i8) {
+/// Shown instead of the turn screens when the terminal is too small for them
+fn print_too_small(win: *mut i8) {
+    const MESSAGE: &str = "Terminal too small";
+    let (win_height, win_width) = get_win_size(win);
     erase();
-    print_centered_left_align(win, &HELP_LINES);
-    while getch() != KEY_NEWLINE {};
+    mvaddnstr(win_height / 2, ((win_width - MESSAGE.len() as i32) / 2).max(0), MESSAGE, win_width);
 }
 
-fn randomize_dice(dice: &mut [u8], to_randomize: &Vec<u8>) {
-    for i in to_randomize {
-        dice[*i as usize] = rand::thread_rng().gen_range(1..=6);
+/// The rules, followed by the house rules that change them
+fn help(win: *mut i8, rules: Rules, house_rules: &HouseRules) {
+    erase();
+    let rerolls = match house_rules.rerolls() {
+        1 => "once".to_string(),
+        n => format!("up to {} times", n),
+    };
+    let intro = [
+        format!("On each turn every player rolls {} dice.", rules.dice()),
+        format!("They can save any dice they want and reroll the dice {}.", rerolls),
+        format!("Once the dice are rolled, the player crosses out one of the {} combinations to get points",
+                rules.combinations().len()),
+    ];
+    let mut lines = vec!["                       Yatzee rules.", ""];
+    lines.extend(intro.iter().map(String::as_str));
+    lines.extend(HELP_LINES);
+    let house_lines = house_rules.to_string();
+    if !house_rules.is_empty() {
+        let back = lines.pop().expect("the help ends with how to go back");
+        lines.push("House rules from rules.toml, the optimal AI and hints are off:");
+        lines.extend(house_lines.lines().map(|line| line.trim_end()));
+        lines.extend(["", back]);
     }
+    print_centered_left_align(win, &lines);
+    while getch() != KEY_NEWLINE {};
 }
 
 mod keys {
     pub const KEY_Q      : i32 = 'q' as i32;
+    pub const KEY_A      : i32 = 'a' as i32;
     pub const KEY_H      : i32 = 'h' as i32;
     pub const KEY_L      : i32 = 'l' as i32;
     pub const KEY_J      : i32 = 'j' as i32;
     pub const KEY_K      : i32 = 'k' as i32;
-    pub const KEY_NEWLINE: i32 = '\n' as i32;
-}
-#[allow(dead_code)]
-enum Combinations {
-    Aces             = 0,
-    Twos             = 1,
-    Threes           = 2,
-    Fours            = 3,
-    Fives            = 4,
-    Sixes            = 5,
-    ThreeOfAKind     = 6,
-    FourOfAKind      = 7,
-    FullHouse        = 8,
-    SmallStraight    = 9,
-    LargeStraight    = 10,
-    Yahtzee          = 11,
-    Chance           = 12,
-}
-
-fn calculate_scores(dice: &[u8]) -> [u8; 13] {
-
-    let mut dice: [u8; 5] = dice.try_into().unwrap();
-    dice.sort();
-    let mut scores = [0u8; 13];
-    let mut counts = [0u8; 6];
-
-    // Upper section
-    for i in 1..=6u8 {
-        counts[i as usize - 1] = dice.iter().cloned().filter(|&n| n == i).count() as u8;
-        scores[i as usize - 1] = counts[i as usize - 1] * i;
-    }
-
-
-    let mut most_frequent_count = 0;
-    let mut second_most_frequent_count = 0;
-
-    for &count in counts[0..6].iter() {
-        if count > most_frequent_count {
-            second_most_frequent_count = most_frequent_count;
-            most_frequent_count = count;
-        }
-        else if count > second_most_frequent_count {
-            second_most_frequent_count = count;
-        }
-    }
-
-    if most_frequent_count >= 3 {
-        scores[Combinations::ThreeOfAKind as usize] = dice.iter().sum();
-    }
-    if most_frequent_count >= 4 {
-        scores[Combinations::FourOfAKind as usize] = dice.iter().sum();
-    }
-    if most_frequent_count == 3 && second_most_frequent_count == 2 {
-        scores[Combinations::FullHouse as usize] = 25;
-    }
+    pub const KEY_X      : i32 = 'x' as i32;
+    pub const KEY_SPACE  : i32 = ' ' as i32;
+    pub const KEY_QUESTION_MARK: i32 = '?' as i32;
+    pub const KEY_TAB    : i32 = '\t' as i32;
 
-    let straight_len = {
-        let mut max_len = 0;
-        let mut cur_len = 1;
-        for i in 1..dice.len() {
-            if dice[i] == dice[i - 1] + 1 {
-                cur_len += 1;
-            }
-            else if dice[i] == dice[i - 1] {
-            }
-            else {
-                max_len = std::cmp::max(cur_len, max_len);
-                cur_len = 1;
-            }
-        }
-        std::cmp::max(cur_len, max_len)
-    };
-    if straight_len >= 4 {
-        scores[Combinations::SmallStraight as usize] = 30;
-    }
-    if straight_len >= 5 {
-        scores[Combinations::LargeStraight as usize] = 40;
-    }
-    if most_frequent_count >= 5 {
-        scores[Combinations::Yahtzee as usize] = 50;
-    }
-    scores[Combinations::Chance as usize] = dice.iter().sum();
-    scores
+    pub const KEY_NEWLINE: i32 = '\n' as i32;
 }
-
-fn print_padded_from_right(y: i32, win_width: i32, padding: i32, to_print: &str) {
-    mvaddstr(y, win_width - padding - to_print.len() as i32, to_print);
+/// Prints `to_print` so that it ends `padding` columns left of `right`
+fn print_padded_from_right(y: i32, right: i32, padding: i32, to_print: &str) {
+    mvaddstr(y, right - padding - to_print.len() as i32, to_print);
 }
 
 /// UI
 const DO_NOT_HIGHLIGHT: usize = usize::MAX;
-fn print_combinations(win: *mut i8,
-                      pos: (i32, i32), 
+fn print_combinations(layout: &Layout,
+                      dice: &[u8],
                       scores: &[u8],
                       current_element: usize, 
-                      game_state: &GameState) {
+                      game_state: &GameState,
+                      hint: Option<&Hint>) {
 
-    let mut offset = 0;
-    let (_, win_width) = get_win_size(win);
+    let rules = game_state.rules();
+    if rules.columns() > 1 {
+        print_columns(layout, dice, scores, current_element, game_state, hint);
+        return;
+    }
 
-    const PADDING1: i32 = 3;
-    const SCORE: &str = "Player Score";
-    print_padded_from_right(pos.0 - 10, win_width, PADDING1, SCORE);
-    const PADDING2: i32 = PADDING1 * 2 + SCORE.len() as i32;
-    const VALUE: &str = "Value";
-    print_padded_from_right(pos.0 - 10, win_width, PADDING2, VALUE);
+    let player = &game_state.current_player().data;
+    let (left, right) = (layout.card_x, layout.card_right);
+    let mut y = layout.card_y;
+
+    // The compact scorecard has no header and a single column: the points of
+    // the used boxes and what the dice would score in the others
+    const VALUE_PADDING: i32 = 6;
+    const EXPECTED_PADDING: i32 = 12;
+    if !layout.compact {
+        print_padded_from_right(y, right, 0, "Score");
+        print_padded_from_right(y, right, VALUE_PADDING, "Value");
+        // The expected final score of each choice, next to its value
+        if hint.is_some() {
+            print_padded_from_right(y, right, EXPECTED_PADDING, "Expected");
+        }
+        y += 1;
+    }
 
     for (i, &score) in scores.iter().enumerate() {
 
@@ -270,466 +166,1778 @@ fn print_combinations(win: *mut i8,
         };
 
         attron(COLOR_PAIR(pair));
-        mvaddstr(pos.0 - 9 + i as i32 + offset, pos.1, score_index_to_string(i));
+        mvaddstr(y, left, score_index_to_string(rules, i));
 
-
-        let to_print = if !game_state.player.has_used(i) {
-            score.to_string()
-        } else {
+        let to_print = if layout.compact && player.has_used(i) {
+            player.combinations_scores[i].to_string()
+        } else if player.has_used(i) {
             "x".to_string()
+        } else if !player.can_use(i, dice) {
+            "-".to_string()
+        } else if layout.compact {
+            format!("({})", score)
+        } else {
+            score.to_string()
         };
-
-        print_padded_from_right(pos.0 - 9 + i as i32 + offset, win_width, PADDING2, &to_print);
+        let padding = if layout.compact { 0 } else { VALUE_PADDING };
+        print_padded_from_right(y, right, padding, &to_print);
 
         attroff(COLOR_PAIR(pair));
 
-        let to_print = if game_state.player.has_used(i) {
-            game_state.player.combinations_scores[i].to_string()
-        } else {
-            " ".to_string()
-        };
-        print_padded_from_right(pos.0 - 9 + i as i32 + offset, win_width, PADDING1, &to_print);
-
+        if !layout.compact {
+            if let Some(expected) = hint.and_then(|hint| hint.combinations[i]) {
+                print_padded_from_right(y, right, EXPECTED_PADDING, &format!("{:.1}", expected));
+            }
+            if player.has_used(i) {
+                print_padded_from_right(y, right, 0, &player.combinations_scores[i].to_string());
+            }
+        }
+        y += 1;
 
         if i == Combinations::Sixes as usize {
-            offset += 3;
-            mvaddstr(pos.0 - 9 + i as i32 + 1, pos.1, "Total score");
-            let upper_sum = game_state.player.upper_sum();
-            print_padded_from_right(pos.0 - 9 + i as i32 + 1, win_width, PADDING1, &upper_sum.to_string());
+            mvaddstr(y, left, "Total score");
+            print_padded_from_right(y, right, 0, &player.upper_sum(0).to_string());
+
+            mvaddstr(y + 1, left, &bonus_label(player.upper_threshold(), layout.compact));
+            let bonus = if player.got_upper_bonus { player.upper_bonus() } else { 0 };
+            print_padded_from_right(y + 1, right, 0, &bonus.to_string());
+            y += if layout.compact { 2 } else { 3 };
+        }
+    }
+
+    if rules.has_jokers() {
+        mvaddstr(y, left, "Yahtzee bonus");
+        print_padded_from_right(y, right, 0, &(player.yahtzee_bonuses * 100).to_string());
+    }
+}
+
+/// The scorecard of rules with several columns: a box per column shows its
+/// points once used, otherwise what the dice would score there in parentheses
+fn print_columns(layout: &Layout,
+                 dice: &[u8],
+                 scores: &[u8],
+                 current_element: usize,
+                 game_state: &GameState,
+                 hint: Option<&Hint>) {
+    let player = &game_state.current_player().data;
+    let rules = game_state.rules();
+    let combinations = rules.combinations().len();
+    let columns = rules.columns();
+    let (left, right) = (layout.card_x, layout.card_right);
+
+    // Right edge of each column
+    let padding = |column: usize| (columns - 1 - column) as i32 * CELL;
+    let expected_padding = padding(0) + CELL;
+    let mut y = layout.card_y;
+
+    for column in 0..columns {
+        print_padded_from_right(y, right, padding(column), &format!("x{}", rules.column_weight(column)));
+    }
+    if hint.is_some() && !layout.compact {
+        print_padded_from_right(y, right, expected_padding, "Expected");
+    }
+
+    y += 1;
+    for row in 0..combinations {
+        let selected = current_element % combinations == row && current_element != DO_NOT_HIGHLIGHT;
+        let pair = if selected { HIGLIGHT_PAIR } else { REGULAR_PAIR };
+        attron(COLOR_PAIR(pair));
+        mvaddstr(y, left, score_index_to_string(rules, row));
+        attroff(COLOR_PAIR(pair));
 
-            mvaddstr(pos.0 - 9 + i as i32 + 2, pos.1, "Bonus (63 in total or more)");
-            print_padded_from_right(pos.0 - 9 + i as i32 + 2, win_width, PADDING1,
-                if game_state.player.got_upper_bonus { "35" } else { "0" });
+        for column in 0..columns {
+            let i = column * combinations + row;
+            let to_print = if player.has_used(i) {
+                player.combinations_scores[i].to_string()
+            } else if !player.can_use(i, dice) {
+                "-".to_string()
+            } else {
+                format!("({})", scores[i])
+            };
+            let pair = if i == current_element { HIGLIGHT_PAIR } else { REGULAR_PAIR };
+            attron(COLOR_PAIR(pair));
+            print_padded_from_right(y, right, padding(column), &to_print);
+            attroff(COLOR_PAIR(pair));
+        }
+
+        // The best column for the combination
+        let best = hint.filter(|_| !layout.compact).and_then(|hint| (0..columns)
+            .filter_map(|column| hint.combinations[column * combinations + row].map(|v| (column, v)))
+            .max_by(|a, b| a.1.total_cmp(&b.1)));
+        if let Some((column, expected)) = best {
+            print_padded_from_right(y, right, expected_padding,
+                                    &format!("{:.1} x{}", expected, rules.column_weight(column)));
+        }
+        y += 1;
+
+        if rules.combination(row) == Combinations::Sixes {
+            mvaddstr(y, left, "Total score");
+            mvaddstr(y + 1, left, &bonus_label(player.upper_threshold(), layout.compact));
+            for column in 0..columns {
+                print_padded_from_right(y, right, padding(column), &player.upper_sum(column).to_string());
+                let bonus = if player.has_upper_bonus(column) { player.upper_bonus() } else { 0 };
+                print_padded_from_right(y + 1, right, padding(column), &bonus.to_string());
+            }
+            y += if layout.compact { 2 } else { 3 };
         }
     }
+
+    mvaddstr(y, left, "Column total");
+    for column in 0..columns {
+        let total = rules.column_weight(column) * player.column_total(column);
+        print_padded_from_right(y, right, padding(column), &total.to_string());
+    }
+    mvaddstr(y + 1, left, "Yahtzee bonus");
+    print_padded_from_right(y + 1, right, 0, &(player.yahtzee_bonuses * 100).to_string());
 }
 
-/*
- * 
-struct TurnState {
-    dice: [u8; 5],
-    chosen: [bool; 5],
-    rolls_left: i32,
-    current_element: usize,
-    current_row: usize
+/// Shows everyone's scorecards instead of the turn while they're toggled on,
+/// handling a key. Returns whether they were shown.
+fn scorecards_instead(win: *mut i8, paths: &Paths, table: &mut impl Table) -> bool {
+    if !*table.show_scorecards() {
+        return false;
+    }
+    print_scorecards(win, table.game());
+    match getch() {
+        KEY_TAB => *table.show_scorecards() = false,
+        KEY_Q => user_quit(win, paths, table.game(), table.save()),
+        _ => (),
+    }
+    true
 }
-let mut trn = TurnState::default();
-*/
 
-fn player_turn(win: *mut i8, game_state: &mut GameState) {
+/// The scorecards of all the players, a column each, with the totals of both
+/// sections. Rules with several columns show the points of each one, like
+/// 12/-/18 for a box used in the first and the last column only. The two
+/// sections go side by side on terminals too low for them one above the other.
+fn print_scorecards(win: *mut i8, game_state: &GameState) {
+    let rules = game_state.rules();
+    let combinations = rules.combinations().len();
+    let cell = |value: &dyn Fn(&PlayerData, usize) -> Option<i32>| -> Vec<String> {
+        game_state.players.iter()
+            .map(|p| (0..rules.columns())
+                .map(|column| value(&p.data, column).map_or("-".to_string(), |v| v.to_string()))
+                .collect::<Vec<_>>()
+                .join("/"))
+            .collect()
+    };
+    let mut upper = Vec::new();
+    let mut lower = Vec::new();
+    for row in 0..combinations {
+        let points = cell(&|data, column| {
+            let i = column * combinations + row;
+            data.has_used(i).then_some(data.combinations_scores[i] as i32)
+        });
+        let section = if row < 6 { &mut upper } else { &mut lower };
+        section.push((score_index_to_string(rules, row).to_string(), points));
+    }
+    let threshold = game_state.current_player().data.upper_threshold();
+    upper.push(("Upper total".to_string(), cell(&|data, column| Some(data.upper_sum(column)))));
+    upper.push((bonus_label(threshold, true), cell(&|data, column| {
+        Some(if data.has_upper_bonus(column) { data.upper_bonus() as i32 } else { 0 })
+    })));
+    lower.push(("Lower total".to_string(), cell(&|data, column| Some(data.lower_sum(column)))));
+    if rules.has_jokers() {
+        lower.push(("Yahtzee bonus".to_string(),
+                    game_state.players.iter().map(|p| (p.data.yahtzee_bonuses * 100).to_string()).collect()));
+    }
+    lower.push(("Total".to_string(), game_state.players.iter().map(|p| p.data.score.to_string()).collect()));
 
-    let mut dice = [0u8; 5];
-    randomize_dice(&mut dice, &(0..=4).collect());
-    let mut chosen = [false; 5];
-    let mut rolls_left = 2;
-    let mut current_element: usize = 0;
-    let mut current_row = 0;
+    let names: Vec<String> = game_state.players.iter().map(|p| p.name.chars().take(12).collect()).collect();
+    let label_width = upper.iter().chain(&lower).map(|(label, _)| label.len()).max().unwrap_or(0) as i32;
+    let cell_width = 2 + upper.iter().chain(&lower)
+        .flat_map(|(_, cells)| cells.iter().map(|c| c.len()))
+        .chain(names.iter().map(|name| name.chars().count()))
+        .max().unwrap_or(0) as i32;
+    let table_width = label_width + names.len() as i32 * cell_width;
 
+    update(game_state);
     let (win_height, win_width) = get_win_size(win);
-    let mut scores = calculate_scores(&dice);
+    // Under the turn and the scores, above the controls
+    let (top, rows) = (layout::HEADER + 1, win_height - layout::HEADER - 2);
+    let stacked = 2 + (upper.len() + lower.len()) as i32;
+    let side_by_side = 1 + upper.len().max(lower.len()) as i32;
+    let (sections, width) = if stacked <= rows && table_width < win_width {
+        (vec![(top + 1, 0, &upper), (top + 2 + upper.len() as i32, 0, &lower)], table_width)
+    } else if side_by_side <= rows && 2 * table_width + 3 < win_width {
+        (vec![(top + 1, 0, &upper), (top + 1, table_width + 3, &lower)], 2 * table_width + 3)
+    } else {
+        print_too_small(win);
+        return;
+    };
 
-    while rolls_left > 0 {
-        update(game_state);
+    let left = (win_width - width) / 2;
+    for &(_, x, _) in sections.iter().filter(|(y, ..)| *y == top + 1) {
+        for (i, name) in names.iter().enumerate() {
+            let pair = if i == game_state.turn.player { HIGLIGHT_PAIR } else { REGULAR_PAIR };
+            attron(COLOR_PAIR(pair));
+            print_padded_from_right(top, left + x + label_width + (i as i32 + 1) * cell_width, 0, name);
+            attroff(COLOR_PAIR(pair));
+        }
+    }
+    for (y, x, section) in sections {
+        for (row, (label, cells)) in section.iter().enumerate() {
+            let y = y + row as i32;
+            mvaddstr(y, left + x, label);
+            for (i, points) in cells.iter().enumerate() {
+                print_padded_from_right(y, left + x + label_width + (i as i32 + 1) * cell_width, 0, points);
+            }
+        }
+    }
+    mvaddstr(win_height - 1, 0, "Tab: back to the game");
+}
+
+/// Moves the cursor on the scorecard for the arrow keys, returning whether it moved
+fn move_cursor(rules: Rules, current_row: &mut usize, key: i32) -> bool {
+    let combinations = rules.combinations().len();
+    let (column, row) = (*current_row / combinations, *current_row % combinations);
+    *current_row = match key {
+        KEY_UP | KEY_K if row > 0 => *current_row - 1,
+        KEY_DOWN | KEY_J if row + 1 < combinations => *current_row + 1,
+        KEY_LEFT | KEY_H if column > 0 => *current_row - combinations,
+        KEY_RIGHT | KEY_L if column + 1 < rules.columns() => *current_row + combinations,
+        _ => return false,
+    };
+    true
+}
+
+/// The keys of the turn screens, on their last row
+fn turn_controls(has_solver: bool) -> &'static str {
+    if has_solver { "?: hints   Tab: scorecards" } else { "Tab: scorecards" }
+}
 
-        mvaddstr(win_height / 2 - 3, 0, &format!("Rolls left: {}", rolls_left));
-        print_combinations(win, (win_height / 2, win_width / 2), 
-                           &scores, if current_element == 7 {current_row} else {DO_NOT_HIGHLIGHT}, game_state);
+fn player_turn(win: *mut i8, paths: &Paths, hints: &mut HintSolvers, game_state: &mut impl Table) {
+
+    let mut current_element: usize = 0;
+    let mut current_row = 0;
+    let mut show_hint = false;
+    let mut hinted = false;
+    let rules = game_state.game().rules();
+    let has_solver = game_state.game().has_solver();
+    let controls = turn_controls(has_solver);
+
+    // The dice come first, then the buttons and the scorecard
+    let dice_count = rules.dice();
+    let (reroll, hold, scorecard) = (dice_count, dice_count + 1, dice_count + 2);
+    let elements = dice_count + 3;
+    let reroll_x = 1 + dice_count as i32 * 4;
+    let hold_x = reroll_x + 9;
+
+    while game_state.game().rolls_left() > 0 {
+        if scorecards_instead(win, paths, game_state) {
+            continue;
+        }
+        // Waits for the terminal to grow, or anything else that redraws the turn
+        let Some(layout) = Layout::new(get_win_size(win), rules, controls.len() as i32) else {
+            print_too_small(win);
+            getch();
+            continue;
+        };
+        update(game_state.game());
+
+        let dice = game_state.game().dice().to_vec();
+        let chosen = game_state.game().held().to_vec();
+        mvaddstr(layout.message_y, 0, &format!("Rolls left: {}", game_state.game().rolls_left()));
+        let hint = show_hint.then(|| {
+            hints.get(win, paths, rules).hint(&game_state.game().current_player().data, &dice, game_state.game().rolls_left())
+        });
+        print_combinations(&layout, &dice,
+                           &game_state.game().scores(), if current_element == scorecard {current_row} else {DO_NOT_HIGHLIGHT}, game_state.game(),
+                           hint.as_ref());
+        if let Some(Hint { hold: Some((hold, expected)), .. }) = hint {
+            for (i, _) in hold.iter().enumerate().filter(|(_, &h)| h) {
+                mvaddch(layout.dice_y + 1, 1 + i as i32 * 4, '^' as u32);
+            }
+            let advice = if hold.iter().all(|&h| h) { "score the dice as they are" } else { "hold the marked dice" };
+            mvaddstr(layout.dice_y + 2, 0, &format!("Hint: {}", advice));
+            mvaddstr(layout.dice_y + 3, 0, &format!("{:.1} points expected", expected));
+        }
+        mvaddstr(layout.height - 1, 0, controls);
 
-        for i in 0..5 {
-            mvaddstr(win_height / 2 - chosen[i] as i32, 1 + i as i32 * 4, dice[i].to_string().as_ref());
+        for (i, die) in dice.iter().enumerate() {
+            mvaddstr(layout.dice_y - chosen[i] as i32, 1 + i as i32 * 4, die.to_string().as_ref());
         }
 
         match current_element {
-            0..=4 => {
-                mvaddch(win_height / 2, 0 + current_element as i32 * 4, '[' as u32);
-                mvaddch(win_height / 2, 2 + current_element as i32 * 4, ']' as u32);
+            die if die < dice_count => {
+                mvaddch(layout.dice_y, die as i32 * 4, '[' as u32);
+                mvaddch(layout.dice_y, 2 + die as i32 * 4, ']' as u32);
             }
-            5 => {
-                mvaddch(win_height / 2, 20, '[' as u32);
-                mvaddch(win_height / 2, 27, ']' as u32);
+            button if button == reroll => {
+                mvaddch(layout.dice_y, reroll_x - 1, '[' as u32);
+                mvaddch(layout.dice_y, reroll_x + 6, ']' as u32);
             }
-            6 => {
-                mvaddch(win_height / 2, 29, '[' as u32);
-                mvaddch(win_height / 2, 34, ']' as u32);
+            button if button == hold => {
+                mvaddch(layout.dice_y, hold_x - 1, '[' as u32);
+                mvaddch(layout.dice_y, hold_x + 4, ']' as u32);
             }
-            7 => (),
-            _ => unreachable!(),
-
+            _ => (),
         }
 
-        mvaddstr(win_height / 2, 21, "Reroll");
-        mvaddstr(win_height / 2, 30, "Hold");
+        mvaddstr(layout.dice_y, reroll_x, "Reroll");
+        mvaddstr(layout.dice_y, hold_x, "Hold");
 
         let key = getch();
         match key {
-            KEY_LEFT | KEY_H => {
-                current_element = (current_element as i32 - 1).rem_euclid(8) as usize;
+            // Columns of the scorecard come before the other elements
+            KEY_LEFT | KEY_H if current_element != scorecard || !move_cursor(rules, &mut current_row, key) => {
+                current_element = (current_element + elements - 1) % elements;
             }
-            KEY_RIGHT | KEY_L => {
-                current_element = (current_element + 1).rem_euclid(8);
+            KEY_RIGHT | KEY_L if current_element != scorecard || !move_cursor(rules, &mut current_row, key) => {
+                current_element = (current_element + 1) % elements;
             }
 
             KEY_UP | KEY_K => {
                 match current_element {
-                    0..=4 => {
-                        chosen[current_element] = true;
+                    die if die < dice_count => {
+                        game_state.hold(die, true).expect("the dice can be held until the last roll");
                     }
-                    7 => {
-                        if current_row > 0 {
-                            current_row -= 1;
-                        }
+                    element if element == scorecard => {
+                        move_cursor(rules, &mut current_row, key);
                     }
                     _ => (),
                 }
             }
             KEY_DOWN | KEY_J => {
                     match current_element {
-                    0..=4 => {
-                        chosen[current_element] = false;
+                    die if die < dice_count => {
+                        game_state.hold(die, false).expect("the dice can be held until the last roll");
                     }
-                    7 => {
-                        if current_row < 12 {
-                            current_row += 1;
-                        }
+                    element if element == scorecard => {
+                        move_cursor(rules, &mut current_row, key);
                     }
                     _ => (),
                 }
             }
             KEY_NEWLINE => {
                 match current_element { 
-                    0..=4 => {
-                        chosen[current_element] = !chosen[current_element];
+                    die if die < dice_count => {
+                        game_state.toggle_hold(die).expect("the dice can be held until the last roll");
                     }
                     // Hit me
-                    5 => {
-                        let to_randomize = (0..=4).filter(|i| !chosen[*i as usize]).collect();
-                        randomize_dice(&mut dice, &to_randomize);
-                        scores = calculate_scores(&dice);
-                        rolls_left -= 1;
+                    button if button == reroll => {
+                        game_state.reroll().expect("rolls are left in the loop");
                     }
-                    // Hold
-                    6 => {
+                    // Hold, saving the rerolls that are left if the rules allow it
+                    button if button == hold => {
                         break;
                     }
-                    7 => {
-                        if !game_state.player.has_used(current_row) {
-                            game_state.player.add_score(current_row, scores[current_row]);
+                    _ => {
+                        if game_state.score(current_row).is_ok() {
                             return;
                         }
                     }
-                    _ => {
-                        unreachable!();
-                    }
                 }
             }
+            KEY_TAB => {
+                *game_state.show_scorecards() = true;
+            }
+            KEY_QUESTION_MARK if has_solver => {
+                toggle_hint(win, paths, hints, game_state, &mut show_hint, &mut hinted);
+            }
             KEY_Q => {
-                user_quit(win, game_state);
+                user_quit(win, paths, game_state.game(), game_state.save());
             }
             _ => ()
         }
     }
+    let mut dice = game_state.game().dice().to_vec();
     dice.sort();
+    let scores = game_state.game().scores();
 
     loop {
-        update(game_state);
+        if scorecards_instead(win, paths, game_state) {
+            continue;
+        }
+        let Some(layout) = Layout::new(get_win_size(win), rules, controls.len() as i32) else {
+            print_too_small(win);
+            getch();
+            continue;
+        };
+        update(game_state.game());
         for (i, die) in dice.iter().enumerate() {
-            mvaddstr(win_height / 2, 1 + i as i32 * 4, die.to_string().as_ref());
+            mvaddstr(layout.dice_y, 1 + i as i32 * 4, die.to_string().as_ref());
         }
-        mvaddstr(win_height / 2 - 11, 30, "Choose a combination");
+        mvaddstr(layout.message_y, 0, "Choose a combination");
 
-        print_combinations(win, (win_height / 2, win_width / 2), &scores, current_row, game_state);
+        let hint = show_hint.then(|| hints.get(win, paths, rules).hint(&game_state.game().current_player().data, &dice, 0));
+        print_combinations(&layout, &dice, &scores, current_row, game_state.game(), hint.as_ref());
+        mvaddstr(layout.height - 1, 0, controls);
 
         let key = getch();
         match key {
-            KEY_UP | KEY_K => {
-                if current_row > 0 {
-                    current_row -= 1;
-                }
-
+            KEY_UP | KEY_K | KEY_DOWN | KEY_J | KEY_LEFT | KEY_H | KEY_RIGHT | KEY_L => {
+                move_cursor(rules, &mut current_row, key);
             }
-            KEY_DOWN | KEY_J => {
-                if current_row < 12 {
-                    current_row += 1;
-                }
+            KEY_NEWLINE if game_state.score(current_row).is_ok() => {
+                break;
             }
-            KEY_NEWLINE => {
-                if !game_state.player.has_used(current_row) {
-                    game_state.player.add_score(current_row, scores[current_row]);
-                    break;
-                }
+            KEY_TAB => {
+                *game_state.show_scorecards() = true;
+            }
+            KEY_QUESTION_MARK if has_solver => {
+                toggle_hint(win, paths, hints, game_state, &mut show_hint, &mut hinted);
             }
             KEY_Q =>{
-                user_quit(win, game_state);
+                user_quit(win, paths, game_state.game(), game_state.save());
             }
+
             _ => ()
         }
     }
 }
 
-fn score_index_to_string(i: usize) -> &'static str {
-    match i {
-        0 => "Aces",
-        1 => "Twos",
-        2 => "Threes",
-        3 => "Fours",
-        4 => "Fives",
-        5 => "Sixes",
-        6 => "3 of a kind",
-        7 => "4 of a kind",
-        8 => "Full House",
-        9 => "Small Straight",
-        10 => "Large Straight",
-        11 => "Yahtzee (5 of a kind)", 
-        12 => "Chance",
-        _ => panic!("Wrong score index")
+/// Shows or hides the hints, recording the first time they're shown in a turn
+fn toggle_hint(win: *mut i8, paths: &Paths, hints: &mut HintSolvers, table: &mut impl Table,
+               show_hint: &mut bool, hinted: &mut bool) {
+    *show_hint = !*show_hint;
+    if *show_hint {
+        hints.get(win, paths, table.game().rules());
+        if !*hinted {
+            table.use_hint().expect("hints can be looked at during the turn");
+            *hinted = true;
+        }
     }
 }
 
 fn update(game_state: &GameState) {
     erase();
-    addstr(&game_state.turn.to_string());
-    addch('\n' as u32);
-    addstr(&format!("Your score: {}", game_state.player.score));
-    addch('\n' as u32);
-    addstr(&format!("Ai score: {}", game_state.ai.score));
-    addch('\n' as u32);
-}
-
-fn wait(time: Duration) {
-    refresh();
-    std::io::stdout().flush().unwrap();
-    std::thread::sleep(time);
-}
-fn ai_choice(ai: &PlayerData, scores: &[u8]) -> usize {
-    let combinations_left: Vec<_> = ai.combinations_used.iter()
-        .enumerate()
-        .filter(|x| !*x.1)
-        .map(|x| x.0)
+    // Cut to the width of the terminal rather than wrapped into the screen below
+    let (_, win_width) = get_win_size(stdscr());
+    mvaddnstr(0, 0, &format!("{}'s turn ({})", game_state.current_player().name, game_state.turn.n), win_width);
+    let scores: Vec<_> = game_state.players.iter()
+        .map(|p| format!("{}: {}", p.name, p.data.score))
         .collect();
-
-    *combinations_left.iter().max_by_key(|&i| scores[*i])
-        .expect("AI must have at least one combination to choose")
+    mvaddnstr(1, 0, &scores.join("   "), win_width);
 }
 
-fn ai_turn(win: *mut i8, game_state: &mut GameState) {
-    let (win_height, win_width) = get_win_size(win);
-    update(game_state);
-    print_centered(win, "Ai is rolling...");
-    wait(Duration::from_millis(800));
-
-    //////
-    let mut dice = [0u8; 5];
-    randomize_dice(&mut dice, &(0..5).collect());
-    let scores = calculate_scores(&dice);
-    let choice = ai_choice(&game_state.ai, &scores);
-    game_state.ai.add_score(choice, scores[choice]);
-    //////
-
-    update(&game_state);
-    print_centered(win, "Ai rolled:");
-    let strs: Vec<_> = dice.iter().map(|x| x.to_string()).collect();
-    let joined = strs.join(", ");
-    mvaddstr(win_height / 2 + 2, (win_width - joined.len() as i32) / 2, &joined);
-    wait(Duration::from_millis(1000));
-
-    let message = format!("Ai chose: {} for {} points",
-                          score_index_to_string(choice),
-                          scores[choice]);
-    mvaddstr(win_height / 2 + 4, (win_width - message.len() as i32) / 2, &message);
-    wait(Duration::from_millis(1500));
-}
-
-fn user_quit(win: *mut i8, game_state: &GameState) {
-    let mut ans = false;
-    let (win_height, win_width) = get_win_size(win);
-    let (center_y, center_x) = (win_height / 2, win_width / 2);
+/// The AI playing at a local table, each of its moves shown for a while
+struct ShownAiTable<'a> {
+    win: *mut i8,
+    table: &'a mut LocalTable,
+    name: String,
+    /// Which roll of the turn the dice are, out of `rolls`
+    roll: u32,
+    rolls: u32,
+}
 
-    loop {
-        update(game_state);
-        print_centered(win, "Are you sure you want to quit?");
-        //  | | | |
-        mvaddstr(center_y + 2, center_x - 4, "yes");
-        mvaddstr(center_y + 2, center_x + 2, "no");
-        if ans {
-            mvaddch(center_y + 2, center_x - 5, '[' as u32);
-            mvaddch(center_y + 2, center_x - 1, ']' as u32);
+impl ShownAiTable<'_> {
+    fn print_dice(&self, title: &str, dice: &str, message: &str) {
+        let game_state = &self.table.game_state;
+        if self.table.show_scorecards {
+            print_scorecards(self.win, game_state);
+            return;
         }
-        else {
-            mvaddch(center_y + 2, center_x + 1, '[' as u32);
-            mvaddch(center_y + 2, center_x + 4, ']' as u32);
+        let (win_height, win_width) = get_win_size(self.win);
+        // The turn screens of the human players fit wherever this one does
+        let footer = turn_controls(game_state.has_solver()).len() as i32;
+        if Layout::new((win_height, win_width), game_state.rules(), footer).is_none() {
+            print_too_small(self.win);
+            return;
         }
-        let key = getch();
-        match key {
-            KEY_LEFT | KEY_H | KEY_RIGHT | KEY_L => {
-                ans = !ans;
-            }
-            KEY_NEWLINE if ans => {
-                    endwin();
-                    std::process::exit(0);
+        update(game_state);
+        print_centered(self.win, title);
+        mvaddstr(win_height / 2 + 2, (win_width - dice.len() as i32) / 2, dice);
+        mvaddstr(win_height / 2 + 4, ((win_width - message.len() as i32) / 2).max(0), message);
+    }
+
+    /// Shows the dice for `time`, drawing them again when the terminal is
+    /// resized or the scorecards are toggled
+    fn show(&mut self, title: &str, dice: &str, message: &str, time: Duration) {
+        let end = Instant::now() + time;
+        self.print_dice(title, dice, message);
+        refresh();
+        while let Some(left) = end.checked_duration_since(Instant::now()).filter(|left| !left.is_zero()) {
+            timeout(left.as_millis().max(1) as i32);
+            let key = getch();
+            timeout(-1);
+            if key == KEY_TAB {
+                self.table.show_scorecards = !self.table.show_scorecards;
             }
-            KEY_NEWLINE | KEY_Q => {
-                break;
+            if key == KEY_RESIZE || key == KEY_TAB {
+                self.print_dice(title, dice, message);
+                refresh();
             }
-            _ => (),
         }
     }
-}
 
-struct Highscore {
-    name: String,
-    score: i32
+    /// Tumbles the dice that aren't held for show, they're already rolled
+    fn show_roll(&mut self) {
+        let game_state = &self.table.game_state;
+        let held = game_state.held().to_vec();
+        let dice = dice_string(game_state.dice(), &held);
+        for _ in 0..8 {
+            let frame: Vec<u8> = self.table.game_state.dice().iter().zip(&held)
+                .map(|(&d, &h)| if h { d } else { rand::thread_rng().gen_range(1..=6) })
+                .collect();
+            self.show(&format!("{} is rolling...", self.name), &dice_string(&frame, &held), "", Duration::from_millis(100));
+        }
+        self.show(&format!("{} rolled ({}/{}):", self.name, self.roll, self.rolls), &dice, "", Duration::from_millis(1000));
+    }
 }
 
-impl std::fmt::Display for Highscore {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}: {}", self.name, self.score)
+impl Moves for ShownAiTable<'_> {
+    fn game(&self) -> &GameState {
+        self.table.game()
+    }
+    fn hold(&mut self, index: usize, held: bool) -> Result<(), GameError> {
+        self.table.hold(index, held)
+    }
+    fn reroll(&mut self) -> Result<(), GameError> {
+        let dice = dice_string(self.table.game_state.dice(), self.table.game_state.held());
+        self.show(&format!("{} holds:", self.name), &dice, "", Duration::from_millis(1000));
+        self.table.reroll()?;
+        self.roll += 1;
+        Ok(())
+    }
+    fn score(&mut self, index: usize) -> Result<u8, GameError> {
+        let title = format!("{} rolled ({}/{}):", self.name, self.roll, self.rolls);
+        let dice = dice_string(self.table.game_state.dice(), self.table.game_state.held());
+        let points = self.table.score(index)?;
+        let message = format!("{} chose: {} for {} points", self.name, box_name(self.game().rules(), index), points);
+        self.show(&title, &dice, &message, Duration::from_millis(1500));
+        Ok(points)
     }
 }
 
-use std::fs::File;
-use std::io::{self, BufReader, BufRead};
-
-fn create_highscores(path: &str) -> Result<(), io::Error> {
-    let mut f = File::create(path)?;
-    write!(f, "Developer: 205\nVladimir: 120\n")
+fn ai_turn(win: *mut i8, table: &mut LocalTable, strategy: &dyn Strategy) {
+    let name = table.game_state.current_player().name.clone();
+    let rolls = 1 + table.game_state.rolls_left();
+    let mut shown = ShownAiTable { win, table, name, roll: 1, rolls };
+    play_turn_with(strategy, &mut shown, ShownAiTable::show_roll);
 }
 
-fn get_highscores(path: &str) -> Result<Vec<Highscore>, io::Error> {
-
-    if !std::path::Path::new(path).exists() {
-        create_highscores(path)?
-    }
-
-    if let Ok(file) = File::open(path) {
-        let reader = BufReader::new(file);
+fn network_error(e: &io::Error) -> ! {
+    endwin();
+    eprintln!("Network error: {}", e);
+    std::process::exit(1);
+}
 
-        let mut highscores = Vec::new();
+/// A human's turn in a game hosted over the network. Every move is sent to
+/// the host and the game is updated from the state it sends back.
+struct RemoteTable<'a> {
+    client: &'a Client,
+    game_state: GameState,
+    show_scorecards: bool,
+}
 
-        for line in reader.lines() {
-            match line?.split(':').collect::<Vec<_>>()[..] {
-                [name, score_str] => {
-                    if let Ok(score) = score_str.trim().parse() {
-                            highscores.push(Highscore { name: name.to_string(), score });
-                    }
+impl RemoteTable<'_> {
+    /// Sends a move and waits for the host to accept or reject it
+    fn play(&mut self, message: ClientMessage) -> Result<u8, GameError> {
+        self.client.send(&message).unwrap_or_else(|e| network_error(&e));
+        let mut points = 0;
+        loop {
+            match self.client.recv().unwrap_or_else(|e| network_error(&e)) {
+                ServerMessage::State(view) => {
+                    self.game_state = view.to_game_state();
+                    return Ok(points);
                 }
+                ServerMessage::Scored { points: p, .. } => points = p,
+                ServerMessage::Error { error: Some(error), .. } => return Err(error),
+                ServerMessage::Error { message, .. } => network_error(&io::Error::other(message)),
                 _ => (),
             }
         }
-        Ok(highscores)
-    } else {
-        Ok(Vec::new())
     }
 }
 
-fn write_highscores(path: &str, hs: &Vec<Highscore>) {
-    let mut file = File::create(path)
-                    .unwrap_or_else(|e| panic!("highscore file should open {}", e));
-    for h in hs {
-        writeln!(file, "{}", h).expect("write shouldn't fail");
+impl Moves for RemoteTable<'_> {
+    fn game(&self) -> &GameState {
+        &self.game_state
+    }
+    fn hold(&mut self, index: usize, held: bool) -> Result<(), GameError> {
+        let mut all_held = self.game_state.held().to_vec();
+        all_held[index] = held;
+        self.play(ClientMessage::Hold { held: all_held }).map(|_| ())
+    }
+    fn reroll(&mut self) -> Result<(), GameError> {
+        self.play(ClientMessage::Reroll).map(|_| ())
+    }
+    fn score(&mut self, index: usize) -> Result<u8, GameError> {
+        self.play(ClientMessage::Score { combination: index })
     }
 }
 
-macro_rules! wait_for {
-    ($key:ident) => {
-        loop {
-            let key = getch();
-            match key {
-                KEY_Q => return,
-                $key => break,
-                _ => (),
-            }
-        }
-    };
+impl Table for RemoteTable<'_> {
+    fn use_hint(&mut self) -> Result<(), GameError> {
+        self.play(ClientMessage::Hint).map(|_| ())
+    }
+    /// The host has the game
+    fn save(&self) -> Option<SavedGame> {
+        None
+    }
+    fn show_scorecards(&mut self) -> &mut bool {
+        &mut self.show_scorecards
+    }
 }
 
-fn endgame_and_highscores(win: *mut i8, game_state: &GameState) {
-    use std::cmp::Ordering;
-
-    let (s1, s2) = (format!("Your score: {}\n", game_state.player.score), 
-                    format!("Ai score: {}", game_state.ai.score));
+/// The key pressed while waiting for the host, if any
+fn poll_key() -> i32 {
+    nodelay(stdscr(), true);
+    let key = getch();
+    nodelay(stdscr(), false);
+    key
+}
 
-    let mut final_message = vec![
-        "Game ended!",
-        match game_state.player.score.cmp(&game_state.ai.score) {
-            Ordering::Greater => {
-                "Congratulations! You won!"
-            }
-            Ordering::Equal => {
-                "It's a tie!"
+/// Plays a game hosted over the network until it's over. Returns the final
+/// state, the seats played on this terminal and the difficulty of the AIs.
+fn network_game(win: *mut i8, paths: &Paths, client: &Client, title: &str) -> (GameState, Vec<usize>, Difficulty) {
+    let mut hints = HintSolvers::default();
+    let mut local_seats = Vec::new();
+    let mut difficulty = Difficulty::Heuristic;
+    let mut lobby: Vec<String> = Vec::new();
+
+    let game_state = loop {
+        let (win_height, win_width) = get_win_size(win);
+        erase();
+        mvaddstr(win_height / 2 - 4, win_width / 2 - 20, title);
+        mvaddstr(win_height / 2 - 3, win_width / 2 - 20, "Waiting for the players to join...");
+        for (i, seat) in lobby.iter().enumerate() {
+            mvaddstr(win_height / 2 - 1 + i as i32, win_width / 2 - 20, seat);
+        }
+        refresh();
+
+        match client.recv_timeout(Duration::from_millis(100)).unwrap_or_else(|e| network_error(&e)) {
+            Some(ServerMessage::Lobby { players }) => {
+                lobby = players.iter().enumerate()
+                    .map(|(i, seat)| match (seat.kind, seat.joined) {
+                        (PlayerKind::AI, _) => format!("{}. {} (AI)", i + 1, seat.name),
+                        (PlayerKind::Human, true) => format!("{}. {}", i + 1, seat.name),
+                        (PlayerKind::Human, false) => format!("{}. (waiting)", i + 1),
+                    })
+                    .collect();
             }
-            Ordering::Less => {
-                "You lost!"
+            Some(ServerMessage::Welcome { seat, difficulty: d }) => {
+                local_seats.push(seat);
+                difficulty = d;
             }
-        },
-        "",
-        "",
-        &s1,
-        &s2,
-        "",
-    ];
+            Some(ServerMessage::State(view)) => break view.to_game_state(),
+            Some(ServerMessage::Error { message, .. }) => network_error(&io::Error::other(message)),
+            _ => (),
+        }
+        if poll_key() == KEY_Q {
+            endwin();
+            std::process::exit(0);
+        }
+    };
 
-    const HIGHSCORE_PATH: &str = "/.config/yahtzee.txt";
-    let highscore_path = std::env::var("HOME").unwrap_or("".to_string()) + HIGHSCORE_PATH;
-    let mut highscores = get_highscores(&highscore_path)
-        //FIXME: don't panic
-        .unwrap_or_else(|e| panic!("highscore file should open {}", e));
+    let mut table = RemoteTable { client, game_state, show_scorecards: false };
+    let mut last_move = String::new();
+    while !table.game_state.is_over() {
+        if local_seats.contains(&table.game_state.turn.player) {
+            player_turn(win, paths, &mut hints, &mut table);
+            continue;
+        }
 
-    if highscores.first().map(|x| x.score < game_state.player.score).unwrap_or(true) {
-        final_message[2] = "New highscore! Press h to see highscores";
-    } else {
-        final_message[2] = "Press h to see highscores";
+        let game_state = &table.game_state;
+        let (win_height, win_width) = get_win_size(win);
+        // The turn screens of the players here fit wherever this one does
+        let footer = turn_controls(game_state.has_solver()).len() as i32;
+        if table.show_scorecards {
+            print_scorecards(win, game_state);
+        } else if Layout::new((win_height, win_width), game_state.rules(), footer).is_some() {
+            update(game_state);
+            print_centered(win, &format!("{} is playing...", game_state.current_player().name));
+            let s = dice_string(game_state.dice(), game_state.held());
+            mvaddstr(win_height / 2 + 2, (win_width - s.len() as i32) / 2, &s);
+            mvaddstr(win_height / 2 + 4, ((win_width - last_move.len() as i32) / 2).max(0), &last_move);
+        } else {
+            print_too_small(win);
+        }
+        refresh();
+
+        match client.recv_timeout(Duration::from_millis(100)).unwrap_or_else(|e| network_error(&e)) {
+            Some(ServerMessage::State(view)) => table.game_state = view.to_game_state(),
+            Some(ServerMessage::Scored { seat, combination, points }) => {
+                last_move = format!("{} chose: {} for {} points",
+                                    game_state.players[seat].name,
+                                    box_name(game_state.rules(), combination),
+                                    points);
+            }
+            Some(ServerMessage::Left { seat }) => {
+                last_move = format!("{} left, an AI plays for them", game_state.players[seat].name);
+            }
+            _ => (),
+        }
+        match poll_key() {
+            KEY_Q => user_quit(win, paths, &table.game_state, None),
+            KEY_TAB => table.show_scorecards = !table.show_scorecards,
+            _ => (),
+        }
     }
+    (table.game_state, local_seats, difficulty)
+}
 
-    print_centered_left_align(win, &final_message);
+fn ask_name(win: *mut i8) -> String {
+    let default = std::env::var("USER").unwrap_or("Player".to_string());
+    erase();
+    print_centered(win, &format!("Your name ({}): ", default));
+    echo();
+    curs_set(CURSOR_VISIBILITY::CURSOR_VISIBLE);
+    let mut input = String::new();
+    getstr(&mut input);
+    curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
+    noecho();
+    if input.trim().is_empty() { default } else { input.trim().to_string() }
+}
 
+/// Plays back a replay: step through it with the arrow keys or let it play
+fn replay(win: *mut i8, replay: &Replay) {
+    let frames = replay.frames();
+    let mut current = 0;
+    let mut auto_play = false;
 
-    wait_for!(KEY_H);
+    loop {
+        let frame = &frames[current];
+        let game_state = &frame.game_state;
+        let name = &game_state.current_player().name;
+        let (message, highlight) = match frame.event {
+            ReplayEvent::Start { .. } => (format!("{} rolled ({}):", name, roll_progress(game_state)), DO_NOT_HIGHLIGHT),
+            ReplayEvent::Hold { .. } => (format!("{} holds:", name), DO_NOT_HIGHLIGHT),
+            ReplayEvent::Hint { .. } => (format!("{} looked at a hint:", name), DO_NOT_HIGHLIGHT),
+
+            ReplayEvent::Roll { .. } => (format!("{} rolled ({}):", name, roll_progress(game_state)), DO_NOT_HIGHLIGHT),
+            ReplayEvent::Score { combination, points, .. } => {
+                (format!("{} chose: {} for {} points", name, box_name(game_state.rules(), combination), points), combination)
+            }
+        };
 
-    clear();
+        let controls = format!("Step {}/{} ({} AI)   h/l: back/forward   Space: {}   q: quit",
+                               current + 1, frames.len(), replay.difficulty(),
+                               if auto_play { "pause" } else { "play" });
+        match Layout::new(get_win_size(win), game_state.rules(), controls.len() as i32) {
+            Some(layout) => {
+                update(game_state);
+                mvaddstr(layout.message_y, 0, &message);
+                mvaddstr(layout.dice_y, 0, &dice_string(game_state.dice(), game_state.held()));
+                print_combinations(&layout, game_state.dice(), &game_state.scores(), highlight, game_state, None);
+
+                mvaddnstr(layout.height - 1, 0, &controls, layout.width);
+            }
+            None => print_too_small(win),
+        }
+
+        timeout(if auto_play { 1000 } else { -1 });
+        let key = getch();
+        timeout(-1);
+        match key {
+            ERR if auto_play => {
+                if current + 1 < frames.len() {
+                    current += 1;
+                } else {
+                    auto_play = false;
+                }
+            }
+            KEY_LEFT | KEY_H => {
+                current = current.saturating_sub(1);
+            }
+            KEY_RIGHT | KEY_L if current + 1 < frames.len() => {
+                current += 1;
+            }
+            KEY_SPACE => {
+                auto_play = !auto_play;
+            }
+            KEY_Q => {
+                return;
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Draws the final scores as bars, one row per range of scores from `y` to
+/// `bottom`. The ranges get wider until they all fit.
+fn print_histogram(stats: &PlayerStats, y: i32, x: i32, bottom: i32, width: i32) {
+    let mut bucket = 25;
+    let mut histogram = stats.histogram(bucket);
+    while histogram.len() as i32 > bottom - y && bucket < 400 {
+        bucket *= 2;
+        histogram = stats.histogram(bucket);
+    }
+    let most = histogram.iter().map(|&(_, n)| n).max().unwrap_or(1);
+    for (i, &(from, n)) in histogram.iter().enumerate().take((bottom - y).max(0) as usize) {
+        let label = format!("{:>4}-{:<4} ", from, from + bucket - 1);
+        let bar = (width - label.len() as i32 - 5).max(1) as usize * n / most;
+        mvaddstr(y + i as i32, x, &label);
+        attron(COLOR_PAIR(HIGLIGHT_PAIR));
+        addstr(&" ".repeat(bar));
+        attroff(COLOR_PAIR(HIGLIGHT_PAIR));
+        addstr(&format!(" {}", n));
+    }
+}
+
+/// Shows the stats of everyone who played on this terminal, one player at a time
+fn stats_screen(win: *mut i8, paths: &Paths) {
+    let stats = match Stats::load(&paths.stats()) {
+        Ok(stats) if !stats.is_empty() => stats,
+        result => {
+            let message = match result {
+                Err(e) => format!("Couldn't read the stats: {}", e),
+                Ok(_) => "No games played yet.".to_string(),
+            };
+            erase();
+            print_centered_left_align(win, &[&message, "Press any key to continue."]);
+            getch();
+            return;
+        }
+    };
+    // A page for every player in every ruleset
+    let pages: Vec<(Rules, &String, &PlayerStats)> = stats.rulesets.iter()
+        .flat_map(|(&rules, players)| players.iter().map(move |(name, player)| (rules, name, player)))
+        .collect();
+    let mut current = 0;
+
+    loop {
+        erase();
+        let (win_height, win_width) = get_win_size(win);
+        let (rules, name, player) = pages[current];
+
+        let mut lines = vec![
+            format!("{}, {} ({}/{})", name, rules.title(), current + 1, pages.len()),
+            String::new(),
+            format!("Games played:  {}", player.games),
+            format!("Average score: {:.1}", player.average_score()),
+            format!("Upper bonus:   {:.0}%", player.upper_bonus_rate() * 100.0),
+            format!("{:<15}{:.0}%", format!("{}:", rules.title()), player.yahtzee_rate() * 100.0),
+            String::new(),
+        ];
+        for difficulty in Difficulty::ALL {
+            if let Some(record) = player.results.get(&difficulty) {
+                lines.push(format!("vs {:<9} {} won, {} lost, {} tied",
+                                   difficulty.name(), record.wins, record.losses, record.ties));
+            }
+        }
+        if player.results.is_empty() {
+            lines.push("No games against the AI.".to_string());
+        }
+        lines.push(String::new());
+        lines.push("Final scores:".to_string());
+        for (i, line) in lines.iter().enumerate() {
+            mvaddstr(1 + i as i32, 2, line);
+        }
+        print_histogram(player, 1 + lines.len() as i32, 2, win_height - 2, win_width / 2 - 2);
+
+        mvaddstr(1, win_width / 2, "Average points");
+        for (i, average) in player.combination_averages().iter().enumerate() {
+            mvaddstr(3 + i as i32, win_width / 2, score_index_to_string(rules, i));
+            print_padded_from_right(3 + i as i32, win_width, 3, &format!("{:.1}", average));
+        }
+
+        mvaddstr(win_height - 1, 0, "h/l: previous/next player   q: back");
+        match getch() {
+            KEY_LEFT | KEY_H => {
+                current = current.saturating_sub(1);
+            }
+            KEY_RIGHT | KEY_L if current + 1 < pages.len() => {
+                current += 1;
+            }
+            KEY_Q | KEY_NEWLINE => {
+                return;
+            }
+            _ => (),
+        }
+    }
+}
+
+fn describe_choice(rules: Rules, choice: &Choice, dice: &[u8]) -> String {
+    match choice {
+        Choice::Hold(held) if held.iter().all(|&h| !h) => "reroll all".to_string(),
+        Choice::Hold(held) => {
+            let mut kept: Vec<u8> = dice.iter().zip(held).filter(|(_, h)| **h).map(|(&d, _)| d).collect();
+            kept.sort();
+            let kept: Vec<String> = kept.iter().map(|d| d.to_string()).collect();
+            format!("keep {}", kept.join(" "))
+        }
+        Choice::Score(combination) => box_name(rules, *combination),
+    }
+}
+
+/// Compares every decision of the humans in the recorded game with the
+/// optimal one, one player at a time
+fn analysis_screen(win: *mut i8, paths: &Paths, hints: &mut HintSolvers, replay_file: &std::path::Path,
+                   game_state: &GameState) {
+    let replay = match Replay::load(replay_file) {
+        Ok(replay) => replay,
+        Err(e) => {
+            erase();
+            print_centered_left_align(win, &[&format!("Couldn't read the game: {}", e), "Press any key to exit."]);
+            getch();
+            return;
+        }
+    };
+    let rules = game_state.rules();
+    let solver = hints.get(win, paths, rules);
+    let analyses: Vec<Analysis> = (0..game_state.players.len())
+        .filter(|&seat| game_state.players[seat].kind == PlayerKind::Human)
+        .map(|seat| Analysis::of(&replay, solver, seat))
+        .collect();
+    let mut current = 0;
+    let mut scroll = 0;
+
+    loop {
+        erase();
+        let (win_height, _) = get_win_size(win);
+        let analysis = &analyses[current];
+        let rows = (win_height - 6).max(1) as usize;
+
+        let best = analysis.decisions.iter().filter(|d| d.is_best()).count();
+        mvaddstr(1, 2, &format!("{}: accuracy {:.0}% ({} of {} decisions were the best), {:.1} expected points lost",
+                                game_state.players[analysis.player].name, analysis.accuracy(),
+                                best, analysis.decisions.len(), analysis.loss()));
+        mvaddstr(3, 2, &format!("{:>4}  {:<9}  {:<22} {:<22} {:>5}", "Turn", "Dice", "Your choice", "Best choice", "Loss"));
+        for (i, decision) in analysis.decisions.iter().skip(scroll).take(rows).enumerate() {
+            let dice: Vec<String> = decision.dice.iter().map(|d| d.to_string()).collect();
+            let loss = if decision.is_best() { String::new() } else { format!("{:.1}", -decision.loss()) };
+            let line = format!("{:>4}  {:<9}  {:<22} {:<22} {:>5}", decision.turn, dice.join(" "),
+                               describe_choice(rules, &decision.choice, &decision.dice),
+                               describe_choice(rules, &decision.best, &decision.dice), loss);
+            let pair = if decision.is_best() { REGULAR_PAIR } else { HIGLIGHT_PAIR };
+            attron(COLOR_PAIR(pair));
+            mvaddstr(4 + i as i32, 2, &line);
+            attroff(COLOR_PAIR(pair));
+        }
+
+        mvaddstr(win_height - 1, 0, "j/k: scroll   h/l: previous/next player   q: exit");
+        match getch() {
+            KEY_UP | KEY_K => {
+                scroll = scroll.saturating_sub(1);
+            }
+            KEY_DOWN | KEY_J if scroll + rows < analysis.decisions.len() => {
+                scroll += 1;
+            }
+            KEY_LEFT | KEY_H if current > 0 => {
+                current -= 1;
+                scroll = 0;
+            }
+            KEY_RIGHT | KEY_L if current + 1 < analyses.len() => {
+                current += 1;
+                scroll = 0;
+            }
+            KEY_Q | KEY_NEWLINE => {
+                return;
+            }
+            _ => (),
+        }
+    }
+}
+
+fn choose_rules(win: *mut i8) -> Rules {
+    let mut current = 0;
+    let (win_height, win_width) = get_win_size(win);
+
+    loop {
+        erase();
+        print_centered(win, "Choose the rules:");
+        for (i, rules) in Rules::ALL.iter().enumerate() {
+            let pair = if i == current { HIGLIGHT_PAIR } else { REGULAR_PAIR };
+            attron(COLOR_PAIR(pair));
+            mvaddstr(win_height / 2 + 2 + i as i32, win_width / 2 - 24,
+                     &format!("{:<10} - {}", rules.name(), rules.description()));
+            attroff(COLOR_PAIR(pair));
+        }
+
+        match getch() {
+            KEY_UP | KEY_K => {
+                current = current.saturating_sub(1);
+            }
+            KEY_DOWN | KEY_J if current < Rules::ALL.len() - 1 => {
+                current += 1;
+            }
+            KEY_NEWLINE => {
+                return Rules::ALL[current];
+            }
+            KEY_Q => {
+                endwin();
+                std::process::exit(0);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The AI levels that can play the game, the optimal one only if it has a solver
+fn choose_difficulty(win: *mut i8, has_solver: bool) -> Difficulty {
+
+    let difficulties: Vec<Difficulty> = Difficulty::ALL.into_iter()
+        .filter(|&d| d != Difficulty::Optimal || has_solver)
+        .collect();
+    let mut current = difficulties.iter().position(|&d| d == Difficulty::Heuristic).unwrap();
+    let (win_height, win_width) = get_win_size(win);
+
+    loop {
+        erase();
+        print_centered(win, "Choose the AI difficulty:");
+        for (i, difficulty) in difficulties.iter().enumerate() {
+            let pair = if i == current { HIGLIGHT_PAIR } else { REGULAR_PAIR };
+            attron(COLOR_PAIR(pair));
+            mvaddstr(win_height / 2 + 2 + i as i32, win_width / 2 - 24,
+                     &format!("{:<10} - {}", difficulty.name(), difficulty.description()));
+            attroff(COLOR_PAIR(pair));
+        }
+
+        match getch() {
+            KEY_UP | KEY_K => {
+                current = current.saturating_sub(1);
+            }
+            KEY_DOWN | KEY_J if current < difficulties.len() - 1 => {
+                current += 1;
+            }
+            KEY_NEWLINE => {
+                return difficulties[current];
+            }
+            KEY_Q => {
+                endwin();
+                std::process::exit(0);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// The optimal AI's solutions of the rules the hints come from, loaded the
+/// first time a player asks for a hint and kept for the rest of the game
+#[derive(Default)]
+struct HintSolvers(BTreeMap<Rules, Solver>);
+
+impl HintSolvers {
+    fn get(&mut self, win: *mut i8, paths: &Paths, rules: Rules) -> &Solver {
+        self.0.entry(rules).or_insert_with(|| {
+            erase();
+            print_centered(win, "Preparing the hints, the first time this takes a minute...");
+            refresh();
+            Solver::load_or_compute(&paths.strategy_cache(rules), rules)
+        })
+    }
+}
+
+/// The answers to quitting a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QuitChoice {
+    SaveAndQuit,
+    Quit,
+    Stay,
+}
+
+impl QuitChoice {
+    /// The label of the answer, quitting being a plain yes when the game can't be saved
+    fn label(self, can_save: bool) -> &'static str {
+        match self {
+            QuitChoice::SaveAndQuit => "save and quit",
+            QuitChoice::Quit if can_save => "quit",
+            QuitChoice::Quit => "yes",
+            QuitChoice::Stay => "no",
+        }
+    }
+}
+
+/// Asks whether to quit, offering to save the game first if there is one to save
+fn user_quit(win: *mut i8, paths: &Paths, game_state: &GameState, save: Option<SavedGame>) {
+    let choices: &[QuitChoice] = if save.is_some() {
+        &[QuitChoice::SaveAndQuit, QuitChoice::Quit, QuitChoice::Stay]
+    } else {
+        &[QuitChoice::Quit, QuitChoice::Stay]
+    };
+    let labels: Vec<&str> = choices.iter().map(|c| c.label(save.is_some())).collect();
+    let mut current = choices.len() - 1;
+    let (win_height, win_width) = get_win_size(win);
+    let (center_y, center_x) = (win_height / 2, win_width / 2);
+
+    loop {
+        update(game_state);
+        print_centered(win, "Are you sure you want to quit?");
+        let width = labels.iter().map(|c| c.len() as i32 + 3).sum::<i32>();
+        let mut x = center_x - width / 2;
+        for (i, choice) in labels.iter().enumerate() {
+            mvaddstr(center_y + 2, x + 1, choice);
+            if i == current {
+                mvaddch(center_y + 2, x, '[' as u32);
+                mvaddch(center_y + 2, x + 1 + choice.len() as i32, ']' as u32);
+            }
+            x += choice.len() as i32 + 3;
+        }
+        let key = getch();
+        match key {
+            KEY_LEFT | KEY_H => {
+                current = (current + choices.len() - 1) % choices.len();
+            }
+            KEY_RIGHT | KEY_L => {
+                current = (current + 1) % choices.len();
+            }
+            KEY_NEWLINE if choices[current] == QuitChoice::SaveAndQuit => {
+                let saved = save.as_ref().expect("only offered with a game to save");
+                match saved.save(&paths.saved_game()) {
+                    Ok(()) => {
+                        endwin();
+                        std::process::exit(0);
+                    }
+                    Err(e) => {
+                        update(game_state);
+                        print_centered(win, &format!("Couldn't save the game: {}", e));
+                        getch();
+                    }
+                }
+            }
+            KEY_NEWLINE if choices[current] == QuitChoice::Quit => {
+                    endwin();
+                    std::process::exit(0);
+            }
+            KEY_NEWLINE | KEY_Q => {
+                break;
+            }
+            _ => (),
+        }
+    }
+}
+
+use std::fs::File;
+use std::io;
+
+macro_rules! wait_for {
+    ($key:ident) => {
+        loop {
+            let key = getch();
+            match key {
+                KEY_Q => return,
+                $key => break,
+                _ => (),
+            }
+        }
+    };
+}
+
+/// Shows the results and adds the scores of the humans playing on this
+/// terminal, i.e. in `local_seats`, to the stats and the highscores
+fn endgame_and_highscores(win: *mut i8, paths: &Paths, game_state: &GameState, difficulty: Difficulty,
+                          local_seats: &[usize]) {
+    let standings = game_state.standings();
+    let winners: Vec<&str> = standings.iter()
+        .filter(|(place, _)| *place == 1)
+        .map(|(_, p)| p.name.as_str())
+        .collect();
+    let humans: Vec<&Player> = local_seats.iter()
+        .map(|&seat| &game_state.players[seat])
+        .filter(|p| p.kind == PlayerKind::Human)
+        .collect();
+
+    let result = match humans[..] {
+        // A single human plays against the AIs
+        [human] if game_state.players.len() > 1 => {
+            if !winners.contains(&human.name.as_str()) {
+                "You lost!".to_string()
+            } else if winners.len() > 1 {
+                "It's a tie!".to_string()
+            } else {
+                "Congratulations! You won!".to_string()
+            }
+        }
+        _ => match winners[..] {
+            [winner] => format!("{} won!", winner),
+            _ => format!("It's a tie between {}!", winners.join(", ")),
+        }
+    };
+    let standing_lines: Vec<_> = standings.iter()
+        .map(|(place, p)| format!("{}. {}: {}", place, p.name, p.data.score))
+        .collect();
+
+    let stats_error = if humans.is_empty() { Ok(()) } else { record_stats(paths, game_state, difficulty, local_seats) };
+    let stats_error = stats_error.err().map(|e| format!("Couldn't update the stats: {}", e)).unwrap_or_default();
+
+    let seed = format!("Seed: {}", game_state.seed());
+    let mut final_message = vec![
+        "Game ended!",
+        &result,
+        "",
+        &stats_error,
+    ];
+    final_message.extend(standing_lines.iter().map(|s| s.as_str()));
+    final_message.push("");
+    final_message.push(&seed);
+    final_message.push("");
+
+    let (highscores, new_best) = match load_highscores(paths, game_state, &humans) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            // Don't overwrite a file we can't read, it may just be from a newer version
+            let error = format!("Couldn't read the highscores: {}", e);
+            final_message[2] = &error;
+            final_message.push("Press any key to exit.");
+            print_centered_left_align(win, &final_message);
+            getch();
+            return;
+        }
+    };
+
+    let rules = game_state.rules();
+    if new_best {
+        final_message[2] = "New highscore! Press h to see highscores";
+    } else {
+        final_message[2] = "Press h to see highscores";
+    }
+
+    print_centered_left_align(win, &final_message);
 
+
+    wait_for!(KEY_H);
+
+    clear();
+
+    let add_prompt = match humans.len() {
+        0 => "Press Enter to exit",
+        1 => "Press Enter to add your score",
+        _ => "Press Enter to add your scores",
+    };
     let mut strs = Vec::new();
     let mut strings = Vec::new();
-    strs.push("HIGHSCORES:");
+    let title = format!("{} HIGHSCORES:", rules.title().to_uppercase());
+    strs.push(title.as_str());
     strs.push("");
-    if highscores.is_empty() {
+    if highscores.of(rules).next().is_none() {
         strs.push("");
-        strs.push("No highscores. Press Enter to add your score");
+        strs.push("No highscores.");
+        strs.push(add_prompt);
     } else {
-        for h in highscores[..std::cmp::min(highscores.len(), 10)].iter() {
+        for h in highscores.of(rules).take(10) {
             strings.push(format!("{}\n", h));
         }
         for s in strings.iter() {
             strs.push(s);
         }
         strs.push("");
-        strs.push("Press Enter to add your score");
+        strs.push(add_prompt);
     }
     print_centered_left_align(win, &strs);
 
     wait_for!(KEY_NEWLINE);
 
-    clear();
-    let (win_height, win_width) = get_win_size(win);
-    mvaddstr(win_height / 2, win_width / 2 - 20, "Enter your name: ");
-    let mut input = String::new();
-    echo();
-    getstr(&mut input);
-    noecho();
+    if humans.is_empty() {
+        return;
+    }
+
+    let added = if humans.len() == 1 { "Added your score!" } else { "Added your scores!" };
+    let message = match add_highscores(paths, highscores, game_state, difficulty, &humans) {
+        Ok(()) => added.to_string(),
+        Err(e) => format!("Couldn't save the highscores: {}", e),
+    };
 
-    let h = Highscore {name: input, score: game_state.player.score};
-    let pos = highscores.binary_search_by(|h1| h.score.cmp(&h1.score)).unwrap_or_else(|e| e);
-    highscores.insert(pos, h);
-    write_highscores(&highscore_path, &highscores);
-    
     clear();
-    print_centered_left_align(win, &["Added your score!", "Press any key to exit."]);
+    print_centered_left_align(win, &[&message, "Press any key to exit."]);
+
     getch();
     
 }
 
-//TODO: yahtzee bonus and joker rules
-//TODO: save highscores
+const ADD_HUMAN: &str = "Add a human player";
+const ADD_NETWORK: &str = "Add a network player";
+const ADD_AI: &str = "Add an AI player";
+const START: &str = "Start the game";
+
+/// Sets up the players. When hosting, seats can also be left for players
+/// joining over the network, their indices are returned too.
+fn choose_players(win: *mut i8, hosting: bool) -> (Vec<Player>, Vec<usize>) {
+    let first_name = std::env::var("USER").unwrap_or("Player 1".to_string());
+    let mut players = vec![
+        Player::new(&first_name, PlayerKind::Human),
+        Player::new("AI", PlayerKind::AI),
+    ];
+    let mut remote = vec![false, false];
+    let actions: &[&str] = if hosting {
+        &[ADD_HUMAN, ADD_NETWORK, ADD_AI, START]
+    } else {
+        &[ADD_HUMAN, ADD_AI, START]
+    };
+    let mut current = players.len() + actions.len() - 1;
+
+    loop {
+        let (win_height, win_width) = get_win_size(win);
+        let items = players.len() + actions.len();
+        let (top, left) = (win_height / 2 - items as i32 / 2 - 2, win_width / 2 - 20);
+
+        erase();
+        mvaddstr(top, left, "Players (Enter renames a human, x removes a player):");
+        for i in 0..items {
+            let line = if i < players.len() {
+                let kind = match players[i].kind {
+                    PlayerKind::Human if remote[i] => "network",
+                    PlayerKind::Human => "human",
+                    PlayerKind::AI => "AI",
+                };
+                format!("{}. {} ({})", i + 1, players[i].name, kind)
+            } else {
+                actions[i - players.len()].to_string()
+            };
+            let pair = if i == current { HIGLIGHT_PAIR } else { REGULAR_PAIR };
+            attron(COLOR_PAIR(pair));
+            // An empty line between the players and the actions
+            mvaddstr(top + 2 + i as i32 + (i >= players.len()) as i32, left, &line);
+            attroff(COLOR_PAIR(pair));
+        }
+
+        match getch() {
+            KEY_UP | KEY_K => {
+                current = current.saturating_sub(1);
+            }
+            KEY_DOWN | KEY_J if current < items - 1 => {
+                current += 1;
+            }
+            KEY_X if current < players.len() && players.len() > 1 => {
+                players.remove(current);
+                remote.remove(current);
+            }
+            KEY_NEWLINE if current < players.len() && players[current].kind == PlayerKind::Human && !remote[current] => {
+                mvaddstr(top + items as i32 + 4, left, "Name: ");
+                let mut input = String::new();
+                echo();
+                curs_set(CURSOR_VISIBILITY::CURSOR_VISIBLE);
+                getstr(&mut input);
+                curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
+                noecho();
+                if !input.trim().is_empty() {
+                    players[current].name = input.trim().to_string();
+                }
+            }
+            KEY_NEWLINE if current < players.len() => (),
+            KEY_NEWLINE => {
+                let action = actions[current - players.len()];
+                if action == START {
+                    let network_seats = (0..players.len()).filter(|&i| remote[i]).collect();
+                    return (players, network_seats);
+                }
+                if players.len() < MAX_PLAYERS {
+                    let kind = if action == ADD_AI { PlayerKind::AI } else { PlayerKind::Human };
+                    let count = players.iter().filter(|p| p.kind == kind).count();
+                    let name = match kind {
+                        PlayerKind::Human => format!("Player {}", count + 1),
+                        PlayerKind::AI => format!("AI {}", count + 1),
+                    };
+                    players.push(Player::new(&name, kind));
+                    remote.push(action == ADD_NETWORK);
+                    current += 1;
+                }
+            }
+            KEY_Q => {
+                endwin();
+                std::process::exit(0);
+            }
+            _ => (),
+        }
+    }
+}
+
+enum Mode {
+    Local,
+    Host(u16),
+    Join(String),
+    Replay(String),
+    Simulate(SimulateArgs),
+    Bot(BotArgs),
+    Tournament(TournamentArgs),
+}
+
+/// A bot on stdin and stdout against AIs
+struct BotArgs {
+    opponents: usize,
+    ai: Difficulty,
+}
+
+fn bot_args(mode: &mut Mode) -> &mut BotArgs {
+    match mode {
+        Mode::Bot(bot) => bot,
+        _ => usage(),
+    }
+}
+
+/// AI against AI games without the UI
+struct SimulateArgs {
+    games: u64,
+    ais: [Difficulty; 2],
+    threads: usize,
+}
+
+fn simulate_args(mode: &mut Mode) -> &mut SimulateArgs {
+    match mode {
+        Mode::Simulate(simulate) => simulate,
+        _ => usage(),
+    }
+}
+
+/// A tournament between AIs and bots
+struct TournamentArgs {
+    /// AI levels and bot programs
+    players: Vec<String>,
+    swiss: bool,
+    /// Rounds of a Swiss tournament, enough to tell a winner apart if not given
+    rounds: Option<usize>,
+    /// Games of every pairing
+    games: u32,
+    csv: Option<std::path::PathBuf>,
+}
+
+fn tournament_args(mode: &mut Mode) -> &mut TournamentArgs {
+    match mode {
+        Mode::Tournament(tournament) => tournament,
+        _ => usage(),
+    }
+}
+
+struct Args {
+    mode: Mode,
+    /// Seed of the dice, random if not given
+    seed: Option<u64>,
+    /// Asked for at the start if not given
+    rules: Option<Rules>,
+    /// Overrides the data directory
+    data_dir: Option<std::path::PathBuf>,
+    /// Plays on stdin and stdout instead of ncurses
+    plain: bool,
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: yahtzee [--data-dir DIR] [--seed N] [--rules yahtzee|yatzy|triple|maxi] [--host PORT | --join ADDRESS:PORT]");
+    eprintln!("       yahtzee [--data-dir DIR] [--seed N] [--rules yahtzee|yatzy|triple|maxi] --plain");
+    eprintln!("       yahtzee [--data-dir DIR] replay FILE");
+    eprintln!("       yahtzee [--data-dir DIR] simulate [--games N] [--ai1 LEVEL] [--ai2 LEVEL] [--seed N] [--rules RULES] [--threads N]");
+    eprintln!("       yahtzee [--data-dir DIR] --bot-protocol [--opponents N] [--ai LEVEL] [--seed N] [--rules RULES]");
+    eprintln!("       yahtzee [--data-dir DIR] tournament PLAYER... [--format round-robin|swiss] [--rounds N] [--games N] [--csv FILE] [--seed N] [--rules RULES]");
+    eprintln!("       (LEVEL is random, greedy, heuristic or optimal, a PLAYER is a LEVEL or a bot program)");
+    std::process::exit(2);
+}
+
+fn parse_args() -> Args {
+    let mut args = Args { mode: Mode::Local, seed: None, rules: None, data_dir: None, plain: false };
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "simulate" && matches!(args.mode, Mode::Local) {
+            let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+            let ais = [Difficulty::Greedy, Difficulty::Heuristic];
+            args.mode = Mode::Simulate(SimulateArgs { games: 10000, ais, threads });
+            continue;
+        }
+        if arg == "tournament" && matches!(args.mode, Mode::Local) {
+            args.mode = Mode::Tournament(TournamentArgs { players: Vec::new(), swiss: false, rounds: None, games: 2, csv: None });
+            continue;
+        }
+        if let Mode::Tournament(tournament) = &mut args.mode {
+            if !arg.starts_with("--") {
+                tournament.players.push(arg);
+                continue;
+            }
+        }
+        if arg == "--bot-protocol" && matches!(args.mode, Mode::Local) {
+            args.mode = Mode::Bot(BotArgs { opponents: 1, ai: Difficulty::Heuristic });
+            continue;
+        }
+        if arg == "--plain" {
+            args.plain = true;
+            continue;
+        }
+        let value = iter.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--host" if matches!(args.mode, Mode::Local) => {
+                args.mode = Mode::Host(value.parse().unwrap_or_else(|_| usage()));
+            }
+            "--join" if matches!(args.mode, Mode::Local) => {
+                args.mode = Mode::Join(value);
+            }
+            "replay" if matches!(args.mode, Mode::Local) => {
+                args.mode = Mode::Replay(value);
+            }
+            "--data-dir" => {
+                args.data_dir = Some(value.into());
+            }
+            "--seed" => {
+                args.seed = Some(value.parse().unwrap_or_else(|_| usage()));
+            }
+            "--rules" => {
+                args.rules = Some(Rules::from_name(&value).unwrap_or_else(|| usage()));
+            }
+            "--games" => match &mut args.mode {
+                Mode::Tournament(tournament) => {
+                    tournament.games = value.parse().ok().filter(|&n| n > 0).unwrap_or_else(|| usage());
+                }
+                mode => simulate_args(mode).games = value.parse().unwrap_or_else(|_| usage()),
+            },
+            "--ai1" | "--ai2" => {
+                let difficulty = Difficulty::from_name(&value).unwrap_or_else(|| usage());
+                simulate_args(&mut args.mode).ais[(arg == "--ai2") as usize] = difficulty;
+            }
+            "--threads" => {
+                simulate_args(&mut args.mode).threads = value.parse().unwrap_or_else(|_| usage());
+            }
+            "--opponents" => {
+                let opponents = value.parse().ok().filter(|&n| n < MAX_PLAYERS).unwrap_or_else(|| usage());
+                bot_args(&mut args.mode).opponents = opponents;
+            }
+            "--ai" => {
+                bot_args(&mut args.mode).ai = Difficulty::from_name(&value).unwrap_or_else(|| usage());
+            }
+            "--format" => {
+                tournament_args(&mut args.mode).swiss = match value.as_str() {
+                    "round-robin" => false,
+                    "swiss" => true,
+                    _ => usage(),
+                };
+            }
+            "--rounds" => {
+                tournament_args(&mut args.mode).rounds = Some(value.parse().ok().filter(|&n| n > 0).unwrap_or_else(|| usage()));
+            }
+            "--csv" => {
+                tournament_args(&mut args.mode).csv = Some(value.into());
+            }
+            _ => usage(),
+        }
+    }
+    // The host rolls the dice and sets the rules of network games and replays have theirs
+    if matches!(args.mode, Mode::Join(_) | Mode::Replay(_)) && (args.seed.is_some() || args.rules.is_some()) {
+        usage();
+    }
+    if let Mode::Tournament(tournament) = &args.mode {
+        if tournament.players.len() < 2 || (tournament.rounds.is_some() && !tournament.swiss) {
+            usage();
+        }
+    }
+    // Only local games have a plain mode
+    if args.plain && !matches!(args.mode, Mode::Local) {
+        usage();
+    }
+    args
+}
+
+/// Plays the games of `yahtzee simulate` and prints how the AIs did
+fn run_simulation(args: &SimulateArgs, paths: &Paths, rules: Rules, house_rules: &HouseRules, seed: u64) {
+    if args.ais.contains(&Difficulty::Optimal) && rules.has_solver() && house_rules.is_empty() {
+        eprintln!("Preparing the optimal AI, the first time this takes a minute...");
+    }
+    let start = std::time::Instant::now();
+    let simulation = simulate(rules, house_rules, &args.ais, &paths.strategy_cache(rules), args.games, seed,
+                              args.threads);
+
+    println!("{} games of {} with seed {} in {:.1}s", simulation.games, rules.title(), seed,
+             start.elapsed().as_secs_f64());
+    if !house_rules.is_empty() {
+        print!("House rules:\n{}", house_rules);
+    }
+    let percent = |n: u64| format!("{:.1}%", n as f64 * 100.0 / simulation.games.max(1) as f64);
+    let per_game = |n: u64| format!("{:.3}", n as f64 / simulation.games.max(1) as f64);
+    let row = |label: &str, values: Vec<String>| {
+        println!("{:<24}{}", label, values.iter().map(|v| format!("{:>12}", v)).collect::<String>());
+    };
+    println!();
+    row("", args.ais.iter().map(|ai| ai.name().to_string()).collect());
+    row("Mean score", simulation.ais.iter().map(|ai| format!("{:.2}", ai.mean())).collect());
+    row("Standard deviation", simulation.ais.iter().map(|ai| format!("{:.2}", ai.std_dev())).collect());
+    row("Wins", simulation.ais.iter().map(|ai| percent(ai.wins)).collect());
+    row("Ties", simulation.ais.iter().map(|ai| percent(ai.ties)).collect());
+    row("Upper bonuses per game", simulation.ais.iter().map(|ai| per_game(ai.upper_bonuses)).collect());
+    if rules.has_jokers() {
+        row("Yahtzee bonuses per game", simulation.ais.iter().map(|ai| per_game(ai.yahtzee_bonuses)).collect());
+    }
+
+    println!("\nAverage points");
+    for index in 0..rules.scorecard_len() {
+        row(&box_name(rules, index), simulation.ais.iter().map(|ai| format!("{:.2}", ai.box_mean(index))).collect());
+    }
+
+    for (difficulty, ai) in args.ais.iter().zip(&simulation.ais) {
+        println!("\nPoints of the {} AI, in % of the games", difficulty);
+        for (index, counts) in ai.boxes.iter().enumerate() {
+            let distribution: Vec<String> = counts.iter()
+                .map(|(points, &n)| format!("{}: {}", points, percent(n)))
+                .collect();
+            println!("{:<24}{}", box_name(rules, index), distribution.join("  "));
+        }
+    }
+}
+
+/// Plays a game against AIs with a bot on stdin and stdout, see [`yahtzee::bot`]
+fn run_bot_protocol(args: &BotArgs, paths: &Paths, rules: Rules, house_rules: &HouseRules, seed: u64) {
+    let mut players = vec![Player::new("Bot", PlayerKind::AI)];
+    players.extend((1..=args.opponents).map(|i| {
+        let name = if args.opponents > 1 { format!("AI {}", i) } else { "AI".to_string() };
+        Player::new(&name, PlayerKind::AI)
+    }));
+    let mut game_state = GameState::with_house_rules(players, rules, house_rules.clone(), seed);
+    if args.ai == Difficulty::Optimal && game_state.has_solver() {
+        eprintln!("Preparing the optimal AI, the first time this takes a minute...");
+    }
+    let strategy = args.ai.strategy(rules, house_rules, &paths.strategy_cache(rules), seed);
+    let bot = Bot::new(io::stdin().lock(), io::stdout());
+    if let Err(e) = bot::play_game(&mut game_state, &mut [(0, bot)], args.ai, strategy.as_ref()) {
+        eprintln!("The bot broke off the game: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Plays `yahtzee tournament`, updates the ratings and prints the standings
+fn run_tournament(args: &TournamentArgs, paths: &Paths, rules: Rules, house_rules: &HouseRules, seed: u64) {
+    let mut contestants = Vec::new();
+    for (i, name) in args.players.iter().enumerate() {
+        if args.players[..i].contains(name) {
+            eprintln!("{} plays in the tournament twice", name);
+            std::process::exit(2);
+        }
+        let entrant = if let Some(difficulty) = Difficulty::from_name(name) {
+            if difficulty == Difficulty::Optimal && rules.has_solver() && house_rules.is_empty() {
+                eprintln!("Preparing the optimal AI, the first time this takes a minute...");
+            }
+            let strategy = difficulty.strategy(rules, house_rules, &paths.strategy_cache(rules), seed.wrapping_add(i as u64));
+            Entrant::Ai { difficulty, strategy }
+        } else if std::path::Path::new(name).is_file() {
+            Entrant::Bot(name.into())
+        } else {
+            eprintln!("{} is neither an AI level nor a bot program", name);
+            std::process::exit(2);
+        };
+        contestants.push(Contestant { name: name.clone(), entrant });
+    }
+    let format = match (args.swiss, args.rounds) {
+        (false, _) => Format::RoundRobin,
+        (true, Some(rounds)) => Format::Swiss { rounds },
+        (true, None) => Format::Swiss { rounds: contestants.len().next_power_of_two().trailing_zeros() as usize },
+    };
+
+    let ratings_path = paths.ratings();
+    let mut ratings = Ratings::load(&ratings_path).unwrap_or_else(|e| {
+        eprintln!("Couldn't read the ratings {}: {}", ratings_path.display(), e);
+        std::process::exit(1);
+    });
+    let before = ratings.clone();
+    let mut tournament = Tournament::new(rules, house_rules.clone(), format, args.games, seed, contestants);
+    tournament.run(|tournament, result| {
+        let [a, b] = result.contestants.map(|c| tournament.contestants[c].name.as_str());
+        if let Some((seat, error)) = &result.forfeit {
+            eprintln!("{} lost a game by breaking it off: {}", [a, b][*seat], error);
+        }
+        ratings.record_game(rules, a, b, result.result());
+    });
+    if let Err(e) = ratings.save(&ratings_path) {
+        eprintln!("Couldn't save the ratings {}: {}", ratings_path.display(), e);
+    }
+
+    let format = match format {
+        Format::RoundRobin => "Round robin".to_string(),
+        Format::Swiss { rounds } => format!("Swiss tournament of {} rounds", rounds),
+    };
+    println!("{} of {} with seed {}, {} games a pairing", format, rules.title(), seed, tournament.games);
+    if !house_rules.is_empty() {
+        print!("House rules:\n{}", house_rules);
+    }
+    let width = tournament.contestants.iter().map(|c| c.name.chars().count()).max().unwrap_or(0).max(6) + 2;
+    println!("\n{:<6}{:<width$}{:>7}{:>7}{:>7}{:>7}{:>7}{:>9}{:>12}{:>14}",
+             "Place", "Player", "Games", "Wins", "Ties", "Losses", "Byes", "Points", "Mean score", "Rating");
+    for (place, &c) in tournament.ranking().iter().enumerate() {
+        let (name, standing) = (&tournament.contestants[c].name, &tournament.standings[c]);
+        let rating = ratings.get(rules, name).rating;
+        let change = rating - before.get(rules, name).rating;
+        println!("{:<6}{:<width$}{:>7}{:>7}{:>7}{:>7}{:>7}{:>9}{:>12.2}{:>14}", place + 1, name, standing.games,
+                 standing.wins, standing.ties, standing.losses, standing.byes, standing.points,
+                 standing.mean_score(), format!("{:.0} ({:+.0})", rating, change));
+    }
+
+    if let Some(csv) = &args.csv {
+        if let Err(e) = File::create(csv).and_then(|file| tournament.write_csv(&ratings, file)) {
+            eprintln!("Couldn't write {}: {}", csv.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn prepare_strategy(win: *mut i8, paths: &Paths, difficulty: Difficulty, rules: Rules, house_rules: &HouseRules, seed: u64)
+    -> Box<dyn Strategy + Send + Sync> {
+    if difficulty == Difficulty::Optimal {
+        erase();
+        print_centered(win, "Preparing the optimal AI, the first time this takes a minute...");
+        refresh();
+    }
+    difficulty.strategy(rules, house_rules, &paths.strategy_cache(rules), seed)
+}
+
+fn host_game(win: *mut i8, paths: &Paths, port: u16, seed: Option<u64>, rules: Rules, house_rules: HouseRules) {
+    let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let difficulty = choose_difficulty(win, rules.has_solver() && house_rules.is_empty());
+    let strategy = prepare_strategy(win, paths, difficulty, rules, &house_rules, seed);
+    let (players, network_seats) = choose_players(win, true);
+
+    let listener = std::net::TcpListener::bind(("0.0.0.0", port)).unwrap_or_else(|e| network_error(&e));
+    let local_seats: Vec<usize> = (0..players.len())
+        .filter(|i| players[*i].kind == PlayerKind::Human && !network_seats.contains(i))
+        .collect();
+    // Only the client of this terminal knows the key to its seats
+    let key = rand::thread_rng().gen();
+    let joins: Vec<ClientMessage> = local_seats.iter()
+        .map(|&seat| ClientMessage::Join { name: players[seat].name.clone(), seat: Some(seat), key: Some(key) })
+        .collect();
+    let config = net::HostConfig {
+        players,
+        open_seats: network_seats,
+        key: Some(key),
+        difficulty,
+        strategy,
+        ai_delay: Duration::from_millis(1000),
+        rules,
+        house_rules,
+        seed: Some(seed),
+    };
+    std::thread::spawn(move || net::host(listener, config));
+
+    // The players on this terminal join like everyone else
+    let client = Client::connect(("127.0.0.1", port)).unwrap_or_else(|e| network_error(&e));
+    for join in joins {
+        client.send(&join).unwrap_or_else(|e| network_error(&e));
+    }
+    let (game_state, local_seats, difficulty) = network_game(win, paths, &client, &format!("Hosting on port {}", port));
+    erase();
+    endgame_and_highscores(win, paths, &game_state, difficulty, &local_seats);
+}
+
+fn join_game(win: *mut i8, paths: &Paths, address: &str) {
+    let name = ask_name(win);
+    let client = Client::connect(address).unwrap_or_else(|e| network_error(&e));
+    client.send(&ClientMessage::Join { name, seat: None, key: None }).unwrap_or_else(|e| network_error(&e));
+    let (game_state, local_seats, difficulty) = network_game(win, paths, &client, &format!("Joined {}", address));
+    erase();
+    endgame_and_highscores(win, paths, &game_state, difficulty, &local_seats);
+}
+
 fn main() {
+    let Args { mode, seed, rules, data_dir, plain } = parse_args();
+    let paths = match Paths::new(data_dir).and_then(|paths| paths.create_dirs().map(|_| paths)) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("Couldn't set up the game's directories: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let house_rules = HouseRules::load(&paths.house_rules()).unwrap_or_else(|e| {
+        eprintln!("Couldn't read the house rules {}: {}", paths.house_rules().display(), e);
+        std::process::exit(1);
+    });
+    if let Mode::Simulate(simulate_args) = &mode {
+        let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+        run_simulation(simulate_args, &paths, rules.unwrap_or_default(), &house_rules, seed);
+        return;
+    }
+    if let Mode::Bot(bot_args) = &mode {
+        let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+        run_bot_protocol(bot_args, &paths, rules.unwrap_or_default(), &house_rules, seed);
+        return;
+    }
+    if let Mode::Tournament(tournament_args) = &mode {
+        let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+        run_tournament(tournament_args, &paths, rules.unwrap_or_default(), &house_rules, seed);
+        return;
+    }
+    if plain {
+        plain::play(&paths, rules, house_rules, seed);
+        return;
+    }
+    let replay_to_play = match &mode {
+        Mode::Replay(path) => Some(Replay::load(std::path::Path::new(path)).unwrap_or_else(|e| {
+            eprintln!("Couldn't read the replay {}: {}", path, e);
+            std::process::exit(1);
+        })),
+        _ => None,
+    };
+
     let win = initscr();
     start_color();
     init_pair(REGULAR_PAIR, COLOR_WHITE, COLOR_BLACK);
@@ -737,81 +1945,114 @@ fn main() {
     keypad(win, true);
     curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
     noecho();
-    
-    let prompts = ["Hello, this is Yahtzee.", "Press 'h' for help.", "Press Enter to play."];
-    print_centered_left_align(win, &prompts);
 
-    if getch() as u8 as char == 'h' {
-        help(win);
+    if let Some(replay_to_play) = &replay_to_play {
+        replay(win, replay_to_play);
+        endwin();
+        return;
     }
 
-    let mut game_state = GameState::default();
+    let save_path = paths.saved_game();
+    let can_continue = matches!(mode, Mode::Local) && save_path.exists();
+    let mut prompts = vec!["Hello, this is Yahtzee.", "Press 'h' for help.", "Press 's' for statistics.", "Press Enter to play."];
+    if can_continue {
+        prompts.push("Press 'c' to continue the saved game.");
+    }
+    prompts.push("Press 'q' to quit.");
 
+    // The help and the statistics come back here
+    let mut saved = None;
     loop {
-        match game_state.turn.player {
-            PlayerKind::Human => {
-                player_turn(win, &mut game_state);
+        erase();
+        print_centered_left_align(win, &prompts);
+        match getch() as u8 as char {
+            'h' => help(win, rules.unwrap_or_default(), &house_rules),
+            's' => stats_screen(win, &paths),
+            'c' if can_continue => {
+                match SavedGame::load(&save_path) {
+                    Ok(game) => {
+                        // The game is saved again when quitting it again
+                        let _ = std::fs::remove_file(&save_path);
+                        saved = Some(game);
+                    }
+                    Err(e) => {
+                        erase();
+                        print_centered_left_align(win, &[&format!("Couldn't load the saved game: {}", e),
+                                                          "Press any key to start a new game."]);
+                        getch();
+                    }
+                }
+                break;
             }
-            PlayerKind::AI => {
-                ai_turn(win, &mut game_state)
+            '\n' => break,
+            'q' => {
+                endwin();
+                return;
             }
-        }
-        game_state.turn.next();
-        #[cfg(debug_assertions)] {
-            game_state.turn.n = 14;
-        }
-        if game_state.turn.n == 14 {
-            erase();
-            endgame_and_highscores(win, &game_state);
-            break;
+            _ => (),
         }
     }
 
-    endwin();
-}
-
-#[cfg(test)]
-mod test {
-    use super::*;
-    
-    #[test]
-    fn three_of_a_kind() {
-        let dice = [1, 2, 3, 3, 3];
-        let scores = calculate_scores(&dice);
-        assert_eq!(scores, [1u8, 2, 9, 0, 0, 0, 12, 0, 0, 0, 0, 0, 12]);
-    }
-    #[test]
-    fn four_of_a_kind() {
-        let dice = [1, 3, 3, 3, 3];
-        let scores = calculate_scores(&dice);
-        assert_eq!(scores, [1u8, 0, 12, 0, 0, 0, 13, 13, 0, 0, 0, 0, 13]);
+    match mode {
+        Mode::Host(port) => {
+            let rules = rules.unwrap_or_else(|| choose_rules(win));
+            host_game(win, &paths, port, seed, rules, house_rules);
+            endwin();
+            return;
+        }
+        Mode::Join(address) => {
+            join_game(win, &paths, &address);
+            endwin();
+            return;
+        }
+        Mode::Replay(_) | Mode::Simulate(_) | Mode::Bot(_) | Mode::Tournament(_) | Mode::Local => (),
     }
 
-    #[test]
-    fn fullhouse() {
-        let dice = [4, 4, 3, 3, 3];
-        let scores = calculate_scores(&dice);
-        assert_eq!(scores, [0u8, 0, 9, 8, 0, 0, 17, 0, 25, 0, 0, 0, 17]);
-    }
+    let mut table = match saved {
+        Some(saved) => LocalTable {
+            game_state: saved.game_state, difficulty: saved.difficulty, recorder: None, show_scorecards: false,
+        },
+        None => {
+            let rules = rules.unwrap_or_else(|| choose_rules(win));
+            let difficulty = choose_difficulty(win, rules.has_solver() && house_rules.is_empty());
+            let (players, _) = choose_players(win, false);
+            let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+            let game_state = GameState::with_house_rules(players, rules, house_rules, seed);
+            LocalTable { game_state, difficulty, recorder: None, show_scorecards: false }
+        }
+    };
+    let replay_file = replay_path(&paths);
+    table.recorder = Recorder::create(&replay_file, &table.game_state, table.difficulty).ok();
 
-    #[test]
-    fn yahtzee() {
-        let dice = [1, 1, 1, 1, 1];
-        let scores = calculate_scores(&dice);
-        assert_eq!(scores, [5u8, 0, 0, 0, 0, 0, 5, 5, 0, 0, 0, 50, 5]);
-    }
+    let strategy = prepare_strategy(win, &paths, table.difficulty, table.game_state.rules(), table.game_state.house_rules(),
+                                    table.game_state.seed());
+    let local_seats: Vec<usize> = (0..table.game_state.players.len()).collect();
+    let mut hints = HintSolvers::default();
 
-    #[test]
-    fn small_straight() {
-        let dice = [3, 2, 1, 4, 3];
-        let scores = calculate_scores(&dice);
-        assert_eq!(scores, [1u8, 2, 6, 4, 0, 0, 0, 0, 0, 30, 0, 0, 13]);
+    loop {
+        match table.game_state.current_player().kind {
+            PlayerKind::Human => {
+                player_turn(win, &paths, &mut hints, &mut table);
+            }
+            PlayerKind::AI => {
+                ai_turn(win, &mut table, strategy.as_ref())
+            }
+        }
+        if table.game_state.is_over() {
+            erase();
+            endgame_and_highscores(win, &paths, &table.game_state, table.difficulty, &local_seats);
+            let recorded = table.recorder.as_ref().is_some_and(Recorder::is_recording);
+            let humans = table.game_state.players.iter().any(|p| p.kind == PlayerKind::Human);
+            if recorded && humans && table.game_state.has_solver() {
+                erase();
+                print_centered_left_align(win, &["Press 'a' to see how well you played,", "any other key to exit."]);
+                if getch() == KEY_A {
+                    analysis_screen(win, &paths, &mut hints, &replay_file, &table.game_state);
+                }
+            }
+            break;
+        }
     }
 
-    #[test]
-    fn large_straight() {
-        let dice = [3, 2, 1, 4, 5];
-        let scores = calculate_scores(&dice);
-        assert_eq!(scores, [1u8, 2, 3, 4, 5, 0, 0, 0, 0, 30, 40, 0, 15]);
-    }
+    endwin();
 }
@@ -0,0 +1,665 @@
+//! Network play: one host runs the game and the players connect to it over TCP.
+//!
+//! The host owns the only [`GameState`], rolls all the dice and plays the AI
+//! seats. Clients only send what their players do and draw the states the host
+//! sends back, so a client can't cheat by rolling its own dice.
+//!
+//! # Protocol
+//!
+//! Every message is a single line of JSON (ending with `\n`) with a `"type"`
+//! field. Unknown fields are ignored.
+//!
+//! ## Client to host
+//!
+//! | Message | Meaning |
+//! |---|---|
+//! | `{"type":"join","name":"Bob"}` | Take the first open seat under the given name. A `"seat"` number can be given to take a particular free open seat instead, or any free human seat along with the host's `"key"`. Only joins with the key can take more than one seat. |
+//! | `{"type":"hold","held":[true,false,false,true,false]}` | Hold exactly these dice. |
+//! | `{"type":"reroll"}` | Reroll the dice that aren't held. |
+//! | `{"type":"score","combination":12}` | Score the dice in a combination, ending the turn. |
+//! | `{"type":"hint"}` | The player looked at a hint this turn, which counts in their `"hints"`. |
+//!
+//! Combinations are numbered from 0 (Aces) in the scorecard order of the game's
+//! rules, which are the `"rules"` of every player's `data`: up to 12 (Chance) in
+//! `"yahtzee"`, up to 14 (Yatzy) in `"yatzy"` and up to 19 (Maxi Yatzy) in `"maxi"`.
+//! In `"triple"` the three columns follow each other, 13 being Aces in the x2
+//! column and 38 Chance in the x3 one. Maxi Yatzy has six dice, so its `"held"`
+//! and `"dice"` have six entries, and a player's unused rerolls are kept in
+//! their `"saved_rolls"`. A game with house rules has them in the `"house_rules"`
+//! of every player's `data`, with the keys of `rules.toml`.
+//! `hold`, `reroll`, `score` and `hint` are only accepted from the owner of the
+//! seat whose turn it is.
+//!
+//! ## Host to client
+//!
+//! | Message | Meaning |
+//! |---|---|
+//! | `{"type":"lobby","players":[{"name":"Alice","kind":"human","joined":true}, ...]}` | The seats while waiting for players to join. |
+//! | `{"type":"welcome","seat":1,"difficulty":"heuristic"}` | The join was accepted, this connection now plays the seat. |
+//! | `{"type":"state", ...}` | The whole game, sent when it starts and after every change. See below. |
+//! | `{"type":"scored","seat":1,"combination":10,"points":40}` | A player scored, sent right before the new state. |
+//! | `{"type":"left","seat":1}` | The player of the seat disconnected, an AI plays it from now on. |
+//! | `{"type":"error","message":"..."}` | The last message was rejected. Rejected game moves also have an `"error"` of `"game_over"`, `"no_rolls_left"` or `{"combination_unavailable":N}`. |
+//!
+//! A state looks like this (`players` is shortened):
+//!
+//! ```json
+//! {"type":"state","turn":{"player":0,"n":1},
+//!  "players":[{"name":"Alice","kind":"human","data":{"rules":"yahtzee","score":0,"combinations_scores":[0,0,0,0,0,0,0,0,0,0,0,0,0],
+//!    "combinations_used":[false,false,false,false,false,false,false,false,false,false,false,false,false],
+//!    "got_upper_bonus":false,"yahtzee_bonuses":0,"scored_yahtzee":[false]},"hints":0,"saved_rolls":0}],
+//!  "dice":[3,1,6,6,2],"held":[false,false,false,false,false],"rolls_left":2,
+//!  "scores":[1,2,3,0,0,12,0,0,0,0,0,0,18],"available":[0,1,2,3,4,5,6,7,8,9,10,11,12],"over":false}
+//! ```
+//!
+//! `turn.player` is the index of the seat whose turn it is, `scores` are what
+//! the dice would score in each combination for that player and `available`
+//! the combinations they can score them in.
+//! The game starts once every human seat is taken, and the host closes the
+//! connections after sending the final state with `"over":true`. That state
+//! also has the `"seed"` the dice were rolled with, which stays secret until then.
+//! Connections made after the game has started can watch it.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{Difficulty, Strategy};
+use crate::game::{GameError, GameState, Player, PlayerKind, Turn};
+use crate::house_rules::HouseRules;
+use crate::rules::Rules;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Join {
+        name: String,
+        #[serde(default)]
+        seat: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key: Option<u64>,
+    },
+    Hold { held: Vec<bool> },
+    Reroll,
+    Score { combination: usize },
+    Hint,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Lobby { players: Vec<LobbySeat> },
+    Welcome { seat: usize, difficulty: Difficulty },
+    State(GameView),
+    Scored { seat: usize, combination: usize, points: u8 },
+    Left { seat: usize },
+    Error {
+        message: String,
+        #[serde(default)]
+        error: Option<GameError>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LobbySeat {
+    pub name: String,
+    pub kind: PlayerKind,
+    pub joined: bool,
+}
+
+/// Everything a client needs to know about the game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameView {
+    pub turn: Turn,
+    pub players: Vec<Player>,
+    pub dice: Vec<u8>,
+    pub held: Vec<bool>,
+    pub rolls_left: u32,
+    pub scores: Vec<u8>,
+    /// Boxes the current player can score the dice in
+    #[serde(default)]
+    pub available: Vec<usize>,
+    pub over: bool,
+    /// Only sent once the game is over, so that nobody can predict the dice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl GameView {
+    pub fn of(game_state: &GameState) -> Self {
+        GameView {
+            turn: game_state.turn.clone(),
+            players: game_state.players.clone(),
+            dice: game_state.dice().to_vec(),
+            held: game_state.held().to_vec(),
+            rolls_left: game_state.rolls_left(),
+            scores: game_state.scores(),
+            available: (0..game_state.rules().scorecard_len()).filter(|&i| game_state.can_score(i)).collect(),
+            over: game_state.is_over(),
+            seed: game_state.is_over().then(|| game_state.seed()),
+        }
+    }
+
+    /// The game as far as the client knows it. Its seed is 0 until the game is over.
+    pub fn to_game_state(&self) -> GameState {
+        GameState::restore(self.turn.clone(), self.players.clone(), self.dice.clone(), self.held.clone(), self.rolls_left,
+                           self.seed.unwrap_or(0))
+    }
+}
+
+pub struct HostConfig {
+    pub players: Vec<Player>,
+    /// Human seats anyone can join. The other human seats can only be taken
+    /// by joining with their seat number and the key.
+    pub open_seats: Vec<usize>,
+    /// Secret of the host's own client, which plays the seats that aren't open
+    pub key: Option<u64>,
+    pub difficulty: Difficulty,
+    /// Plays the AI seats and the seats of players who left
+    pub strategy: Box<dyn Strategy + Send + Sync>,
+    /// Pause between the steps of AI turns so that the players can follow them
+    pub ai_delay: Duration,
+    pub rules: Rules,
+    pub house_rules: HouseRules,
+    /// Seed of the dice, a random one if not given
+    pub seed: Option<u64>,
+}
+
+enum Event {
+    Connected(usize, TcpStream),
+    Message(usize, ClientMessage),
+    Invalid(usize, String),
+    Disconnected(usize),
+}
+
+fn read_events(id: usize, stream: TcpStream, events: Sender<Event>) {
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = match serde_json::from_str(&line) {
+            Ok(message) => Event::Message(id, message),
+            Err(e) => Event::Invalid(id, e.to_string()),
+        };
+        if events.send(event).is_err() {
+            return;
+        }
+    }
+    let _ = events.send(Event::Disconnected(id));
+}
+
+struct Host {
+    config: HostConfig,
+    game: GameState,
+    events: Receiver<Event>,
+    connections: HashMap<usize, TcpStream>,
+    /// Connection playing each seat
+    owners: Vec<Option<usize>>,
+    started: bool,
+}
+
+/// Hosts a game on the listener until it's over, returning the final state
+pub fn host(listener: TcpListener, config: HostConfig) -> io::Result<GameState> {
+    let (sender, events) = mpsc::channel();
+    thread::spawn(move || {
+        for (id, stream) in listener.incoming().enumerate() {
+            let Ok(stream) = stream else { continue };
+            let Ok(reader) = stream.try_clone() else { continue };
+            if sender.send(Event::Connected(id, stream)).is_err() {
+                return;
+            }
+            let sender = sender.clone();
+            thread::spawn(move || read_events(id, reader, sender));
+        }
+    });
+
+    let seats = config.players.len();
+    let seed = config.seed.unwrap_or_else(rand::random);
+    let game = GameState::with_house_rules(config.players.clone(), config.rules, config.house_rules.clone(), seed);
+    let host = Host {
+        config,
+        game,
+        events,
+        connections: HashMap::new(),
+        owners: vec![None; seats],
+        started: false,
+    };
+    host.run()
+}
+
+impl Host {
+    fn run(mut self) -> io::Result<GameState> {
+        while self.waiting_for_players() {
+            let event = self.events.recv().map_err(|_| io::Error::other("stopped listening"))?;
+            self.handle(event);
+        }
+        self.started = true;
+        self.broadcast(&ServerMessage::State(GameView::of(&self.game)));
+
+        while !self.game.is_over() {
+            if self.game.current_player().kind == PlayerKind::AI {
+                self.ai_turn();
+            } else {
+                let event = self.events.recv().map_err(|_| io::Error::other("stopped listening"))?;
+                self.handle(event);
+            }
+        }
+
+        for (_, stream) in self.connections.drain() {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+        Ok(self.game)
+    }
+
+    fn waiting_for_players(&self) -> bool {
+        self.game.players.iter().zip(&self.owners)
+            .any(|(p, owner)| p.kind == PlayerKind::Human && owner.is_none())
+    }
+
+    fn send(&mut self, id: usize, message: &ServerMessage) {
+        let line = serde_json::to_string(message).expect("messages serialize") + "\n";
+        if let Some(stream) = self.connections.get_mut(&id) {
+            // A broken connection shows up as a disconnect on the reading side
+            let _ = stream.write_all(line.as_bytes());
+        }
+    }
+
+    fn broadcast(&mut self, message: &ServerMessage) {
+        let ids: Vec<usize> = self.connections.keys().copied().collect();
+        for id in ids {
+            self.send(id, message);
+        }
+    }
+
+    fn lobby(&self) -> ServerMessage {
+        let players = self.game.players.iter().zip(&self.owners)
+            .map(|(p, owner)| LobbySeat {
+                name: p.name.clone(),
+                kind: p.kind,
+                joined: p.kind == PlayerKind::AI || owner.is_some(),
+            })
+            .collect();
+        ServerMessage::Lobby { players }
+    }
+
+    fn error(&mut self, id: usize, message: &str, error: Option<GameError>) {
+        self.send(id, &ServerMessage::Error { message: message.to_string(), error });
+    }
+
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Connected(id, stream) => {
+                self.connections.insert(id, stream);
+                let message = if self.started {
+                    ServerMessage::State(GameView::of(&self.game))
+                } else {
+                    self.lobby()
+                };
+                self.send(id, &message);
+            }
+            Event::Disconnected(id) => {
+                self.connections.remove(&id);
+                for seat in 0..self.owners.len() {
+                    if self.owners[seat] != Some(id) {
+                        continue;
+                    }
+                    self.owners[seat] = None;
+                    if self.started {
+                        self.game.players[seat].kind = PlayerKind::AI;
+                        self.broadcast(&ServerMessage::Left { seat });
+                    }
+                }
+                if !self.started {
+                    let lobby = self.lobby();
+                    self.broadcast(&lobby);
+                }
+            }
+            Event::Invalid(id, error) => {
+                self.error(id, &format!("invalid message: {}", error), None);
+            }
+            Event::Message(id, ClientMessage::Join { name, seat, key }) => {
+                if self.started {
+                    self.error(id, "the game has already started", None);
+                } else {
+                    self.join(id, &name, seat, key);
+                }
+            }
+            Event::Message(id, _) if !self.started => {
+                self.error(id, "the game hasn't started yet", None);
+            }
+            Event::Message(id, _) if self.owners[self.game.turn.player] != Some(id) => {
+                self.error(id, "it's not your turn", None);
+            }
+            Event::Message(id, ClientMessage::Hold { held }) if held.len() != self.game.dice().len() => {
+                self.error(id, &format!("hold needs {} dice, not {}", self.game.dice().len(), held.len()), None);
+            }
+            Event::Message(id, message) => {
+                let result = match message {
+                    ClientMessage::Hold { held } => {
+                        for (i, &h) in held.iter().enumerate() {
+                            self.game.hold(i, h);
+                        }
+                        self.broadcast(&ServerMessage::State(GameView::of(&self.game)));
+                        Ok(())
+                    }
+                    ClientMessage::Reroll => self.game.reroll()
+                        .map(|_| self.broadcast(&ServerMessage::State(GameView::of(&self.game)))),
+                    ClientMessage::Score { combination } => self.score(combination),
+                    ClientMessage::Hint => {
+                        self.game.use_hint();
+                        self.broadcast(&ServerMessage::State(GameView::of(&self.game)));
+                        Ok(())
+                    }
+                    ClientMessage::Join { .. } => unreachable!(),
+                };
+                if let Err(e) = result {
+                    self.error(id, &e.to_string(), Some(e));
+                }
+            }
+        }
+    }
+
+    fn join(&mut self, id: usize, name: &str, seat: Option<usize>, key: Option<u64>) {
+        let has_key = key.is_some() && key == self.config.key;
+        // Only the host's own client plays several seats
+        if !has_key && self.owners.contains(&Some(id)) {
+            self.error(id, "you already have a seat", None);
+            return;
+        }
+        let free = |s: usize| self.game.players[s].kind == PlayerKind::Human && self.owners[s].is_none();
+        let allowed = |s: usize| self.config.open_seats.contains(&s) || has_key;
+        let seat = match seat {
+            Some(s) if s < self.owners.len() && free(s) && allowed(s) => Some(s),
+            Some(_) => None,
+            None => self.config.open_seats.iter().copied().find(|&s| free(s)),
+        };
+        let Some(seat) = seat else {
+            self.error(id, "there is no free seat", None);
+            return;
+        };
+        self.owners[seat] = Some(id);
+        if !name.trim().is_empty() {
+            self.game.players[seat].name = name.trim().to_string();
+        }
+        self.send(id, &ServerMessage::Welcome { seat, difficulty: self.config.difficulty });
+        let lobby = self.lobby();
+        self.broadcast(&lobby);
+    }
+
+    fn score(&mut self, combination: usize) -> Result<(), GameError> {
+        let seat = self.game.turn.player;
+        let points = self.game.score(combination)?;
+        self.broadcast(&ServerMessage::Scored { seat, combination, points });
+        self.broadcast(&ServerMessage::State(GameView::of(&self.game)));
+        Ok(())
+    }
+
+    /// Waits between AI moves while still taking care of the connections
+    fn pause(&mut self) {
+        thread::sleep(self.config.ai_delay);
+        while let Ok(event) = self.events.try_recv() {
+            self.handle(event);
+        }
+    }
+
+    fn ai_turn(&mut self) {
+        loop {
+            self.pause();
+            let player = &self.game.current_player().data;
+            let held = if self.game.rolls_left() > 0 {
+                self.config.strategy.hold(player, self.game.dice(), self.game.rolls_left())
+            } else {
+                vec![true; self.game.dice().len()]
+            };
+            if held.iter().all(|&h| h) {
+                break;
+            }
+            for (i, &h) in held.iter().enumerate() {
+                self.game.hold(i, h);
+            }
+            self.broadcast(&ServerMessage::State(GameView::of(&self.game)));
+            self.pause();
+            self.game.reroll().expect("rolls are left");
+            self.broadcast(&ServerMessage::State(GameView::of(&self.game)));
+        }
+        let choice = self.config.strategy.choose(&self.game.current_player().data, self.game.dice());
+        self.score(choice).expect("AI chooses a usable combination");
+    }
+}
+
+/// Connection to a host
+pub struct Client {
+    stream: TcpStream,
+    messages: Receiver<io::Result<ServerMessage>>,
+}
+
+impl Client {
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        let reader = stream.try_clone()?;
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let message = line.and_then(|line| serde_json::from_str(&line)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)));
+                let failed = message.is_err();
+                if sender.send(message).is_err() || failed {
+                    return;
+                }
+            }
+            let _ = sender.send(Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the host closed the connection")));
+        });
+        Ok(Client { stream, messages })
+    }
+
+    pub fn send(&self, message: &ClientMessage) -> io::Result<()> {
+        let line = serde_json::to_string(message).expect("messages serialize") + "\n";
+        (&self.stream).write_all(line.as_bytes())
+    }
+
+    /// Waits for the next message from the host
+    pub fn recv(&self) -> io::Result<ServerMessage> {
+        self.messages.recv().unwrap_or_else(|_| Err(io::ErrorKind::UnexpectedEof.into()))
+    }
+
+    /// Waits for the next message for at most `timeout`
+    pub fn recv_timeout(&self, timeout: Duration) -> io::Result<Option<ServerMessage>> {
+        match self.messages.recv_timeout(timeout) {
+            Ok(message) => message.map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ai::GreedyAi;
+
+    fn start_host(players: Vec<Player>, open_seats: Vec<usize>) -> (String, thread::JoinHandle<io::Result<GameState>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let config = HostConfig {
+            players,
+            open_seats,
+            key: Some(42),
+            difficulty: Difficulty::Greedy,
+            strategy: Box::new(GreedyAi),
+            ai_delay: Duration::ZERO,
+            rules: Rules::Yahtzee,
+            house_rules: HouseRules::default(),
+            seed: None,
+        };
+        (addr, thread::spawn(move || host(listener, config)))
+    }
+
+    fn next_state(client: &Client) -> GameView {
+        loop {
+            if let ServerMessage::State(view) = client.recv().unwrap() {
+                return view;
+            }
+        }
+    }
+
+    #[test]
+    fn game_against_ai() {
+        let (addr, host) = start_host(
+            vec![Player::new("Open seat", PlayerKind::Human), Player::new("AI", PlayerKind::AI)],
+            vec![0],
+        );
+        let client = Client::connect(&addr).unwrap();
+        client.send(&ClientMessage::Join { name: "Alice".to_string(), seat: None, key: None }).unwrap();
+
+        let mut view = next_state(&client);
+        assert_eq!(view.players[0].name, "Alice");
+
+        client.send(&ClientMessage::Score { combination: 13 }).unwrap();
+        match client.recv().unwrap() {
+            ServerMessage::Error { error, .. } => assert_eq!(error, Some(GameError::CombinationUnavailable(13))),
+            message => panic!("unexpected {:?}", message),
+        }
+
+        while !view.over {
+            if view.turn.player == 0 {
+                let game_state = view.to_game_state();
+                let choice = (0..13).find(|&i| game_state.can_score(i)).unwrap();
+                client.send(&ClientMessage::Score { combination: choice }).unwrap();
+            }
+            view = next_state(&client);
+        }
+
+        let game_state = host.join().unwrap().unwrap();
+        assert!(game_state.players.iter().all(|p| p.data.is_complete()));
+    }
+
+    #[test]
+    fn only_the_current_player_moves() {
+        let (addr, host) = start_host(
+            vec![Player::new("A", PlayerKind::Human), Player::new("B", PlayerKind::Human)],
+            vec![0, 1],
+        );
+        let a = Client::connect(&addr).unwrap();
+        a.send(&ClientMessage::Join { name: "A".to_string(), seat: None, key: None }).unwrap();
+        let b = Client::connect(&addr).unwrap();
+        b.send(&ClientMessage::Join { name: "B".to_string(), seat: Some(1), key: None }).unwrap();
+        next_state(&a);
+        next_state(&b);
+
+        b.send(&ClientMessage::Reroll).unwrap();
+        loop {
+            if let ServerMessage::Error { message, .. } = b.recv().unwrap() {
+                assert_eq!(message, "it's not your turn");
+                break;
+            }
+        }
+
+        a.send(&ClientMessage::Hold { held: vec![true; 3] }).unwrap();
+        loop {
+            if let ServerMessage::Error { message, .. } = a.recv().unwrap() {
+                assert_eq!(message, "hold needs 5 dice, not 3");
+                break;
+            }
+        }
+
+        a.send(&ClientMessage::Hold { held: vec![true, true, false, false, false] }).unwrap();
+        let view = next_state(&b);
+        assert_eq!(view.held, [true, true, false, false, false]);
+        a.send(&ClientMessage::Hint).unwrap();
+        assert_eq!(next_state(&b).players[0].hints, 1);
+        // A's own view of the hold, the one of the hint is the latest
+        next_state(&a);
+
+        // B leaving hands their seat over to the AI
+        drop(b);
+        for _ in 0..13 {
+            let view = loop {
+                let view = next_state(&a);
+                if view.turn.player == 0 || view.over {
+                    break view;
+                }
+            };
+            if view.over {
+                break;
+            }
+            let choice = (0..13).find(|&i| view.to_game_state().can_score(i)).unwrap();
+            a.send(&ClientMessage::Score { combination: choice }).unwrap();
+        }
+        let game_state = host.join().unwrap().unwrap();
+        assert!(game_state.is_over());
+        assert_eq!(game_state.players[1].kind, PlayerKind::AI);
+    }
+
+    #[test]
+    fn local_seats_need_the_key() {
+        let (addr, _host) = start_host(
+            vec![Player::new("Host", PlayerKind::Human), Player::new("Open seat", PlayerKind::Human)],
+            vec![1],
+        );
+        let remote = Client::connect(&addr).unwrap();
+        for key in [None, Some(7)] {
+            remote.send(&ClientMessage::Join { name: "Mallory".to_string(), seat: Some(0), key }).unwrap();
+            loop {
+                match remote.recv().unwrap() {
+                    ServerMessage::Error { message, .. } => break assert_eq!(message, "there is no free seat"),
+                    ServerMessage::Welcome { seat, .. } => panic!("took seat {}", seat),
+                    _ => {}
+                }
+            }
+        }
+
+        let local = Client::connect(&addr).unwrap();
+        local.send(&ClientMessage::Join { name: "Host".to_string(), seat: Some(0), key: Some(42) }).unwrap();
+        loop {
+            if let ServerMessage::Welcome { seat, .. } = local.recv().unwrap() {
+                break assert_eq!(seat, 0);
+            }
+        }
+        remote.send(&ClientMessage::Join { name: "Mallory".to_string(), seat: None, key: None }).unwrap();
+        assert_eq!(next_state(&remote).players[0].name, "Host");
+    }
+
+    #[test]
+    fn one_seat_per_remote_client() {
+        let (addr, _host) = start_host(
+            vec![Player::new("Host", PlayerKind::Human), Player::new("A", PlayerKind::Human), Player::new("B", PlayerKind::Human)],
+            vec![1, 2],
+        );
+        let a = Client::connect(&addr).unwrap();
+        for _ in 0..2 {
+            a.send(&ClientMessage::Join { name: "A".to_string(), seat: None, key: None }).unwrap();
+        }
+        loop {
+            match a.recv().unwrap() {
+                ServerMessage::Welcome { seat, .. } => assert_eq!(seat, 1),
+                ServerMessage::Error { message, .. } => break assert_eq!(message, "you already have a seat"),
+                _ => {}
+            }
+        }
+
+        let b = Client::connect(&addr).unwrap();
+        b.send(&ClientMessage::Join { name: "B".to_string(), seat: None, key: None }).unwrap();
+        loop {
+            if let ServerMessage::Welcome { seat, .. } = b.recv().unwrap() {
+                break assert_eq!(seat, 2);
+            }
+        }
+    }
+
+    #[test]
+    fn message_format() {
+        let message: ClientMessage = serde_json::from_str(r#"{"type":"score","combination":12}"#).unwrap();
+        assert_eq!(message, ClientMessage::Score { combination: 12 });
+        let message: ClientMessage = serde_json::from_str(r#"{"type":"join","name":"Bob"}"#).unwrap();
+        assert_eq!(message, ClientMessage::Join { name: "Bob".to_string(), seat: None, key: None });
+
+        let error = ServerMessage::Error { message: "no".to_string(), error: Some(GameError::NoRollsLeft) };
+        assert_eq!(serde_json::to_string(&error).unwrap(), r#"{"type":"error","message":"no","error":"no_rolls_left"}"#);
+    }
+}
@@ -0,0 +1,127 @@
+//! Where the game keeps its files, following the XDG base directory spec.
+//!
+//! | Directory | Default | Files |
+//! |---|---|---|
+//! | config | `$XDG_CONFIG_HOME/yahtzee` or `~/.config/yahtzee` | the house rules |
+//! | data | `$XDG_DATA_HOME/yahtzee` or `~/.local/share/yahtzee` | highscores, stats, tournament ratings, the saved game, replays |
+//! | cache | `$XDG_CACHE_HOME/yahtzee` or `~/.cache/yahtzee` | the optimal AI's strategy for each ruleset |
+//!
+//! `--data-dir` replaces the data directory, which then also stands in for the
+//! others if neither `HOME` nor their XDG variables are set.
+
+use std::io;
+use std::path::PathBuf;
+
+use crate::rules::Rules;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paths {
+    pub config: PathBuf,
+    pub data: PathBuf,
+    pub cache: PathBuf,
+    home: Option<PathBuf>,
+}
+
+impl Paths {
+    /// Finds the directories from the environment, `data_dir` overriding the data directory
+    pub fn new(data_dir: Option<PathBuf>) -> io::Result<Self> {
+        Self::resolve(|name| std::env::var(name).ok(), data_dir)
+    }
+
+    fn resolve(var: impl Fn(&str) -> Option<String>, data_dir: Option<PathBuf>) -> io::Result<Self> {
+        let home = var("HOME").filter(|home| !home.is_empty()).map(PathBuf::from);
+        // Relative paths in the XDG variables are invalid and must be ignored
+        let xdg = |name: &str, default: &str| var(name)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| home.as_ref().map(|home| home.join(default)))
+            .map(|dir| dir.join("yahtzee"));
+
+        let data = data_dir.or_else(|| xdg("XDG_DATA_HOME", ".local/share"));
+        let config = xdg("XDG_CONFIG_HOME", ".config").or_else(|| data.clone());
+        let cache = xdg("XDG_CACHE_HOME", ".cache").or_else(|| data.clone());
+        match (config, data, cache) {
+            (Some(config), Some(data), Some(cache)) => Ok(Paths { config, data, cache, home }),
+            _ => Err(io::Error::new(io::ErrorKind::NotFound,
+                                    "HOME isn't set, use --data-dir to choose where to keep the game's files")),
+        }
+    }
+
+    pub fn create_dirs(&self) -> io::Result<()> {
+        for dir in [&self.config, &self.data, &self.cache, &self.replays()] {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    pub fn house_rules(&self) -> PathBuf {
+        self.config.join("rules.toml")
+    }
+    pub fn highscores(&self) -> PathBuf {
+        self.data.join("highscores.json")
+    }
+    /// The highscore file of older versions of the game, `~/.config/yahtzee.txt`
+    pub fn old_highscores(&self) -> Option<PathBuf> {
+        self.home.as_ref().map(|home| home.join(".config/yahtzee.txt"))
+    }
+    pub fn stats(&self) -> PathBuf {
+        self.data.join("stats.json")
+    }
+    pub fn ratings(&self) -> PathBuf {
+        self.data.join("ratings.json")
+    }
+    pub fn saved_game(&self) -> PathBuf {
+        self.data.join("save.json")
+    }
+    pub fn replays(&self) -> PathBuf {
+        self.data.join("replays")
+    }
+    /// Where the optimal AI's strategy for the rules is cached. Rules
+    /// with several columns share the strategy of a single one.
+    pub fn strategy_cache(&self, rules: Rules) -> PathBuf {
+        match rules.column_rules() {
+            Rules::Yahtzee => self.cache.join("strategy.bin"),
+            rules => self.cache.join(format!("strategy-{}.bin", rules)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::Path;
+
+    fn resolve(vars: &[(&str, &str)], data_dir: Option<&str>) -> io::Result<Paths> {
+        let var = |name: &str| vars.iter().find(|(n, _)| *n == name).map(|(_, value)| value.to_string());
+        Paths::resolve(var, data_dir.map(PathBuf::from))
+    }
+
+    #[test]
+    fn defaults_to_home() {
+        let paths = resolve(&[("HOME", "/home/me")], None).unwrap();
+        assert_eq!(paths.house_rules(), Path::new("/home/me/.config/yahtzee/rules.toml"));
+        assert_eq!(paths.data, Path::new("/home/me/.local/share/yahtzee"));
+        assert_eq!(paths.strategy_cache(Rules::Yahtzee), Path::new("/home/me/.cache/yahtzee/strategy.bin"));
+        assert_eq!(paths.strategy_cache(Rules::Yatzy), Path::new("/home/me/.cache/yahtzee/strategy-yatzy.bin"));
+        assert_eq!(paths.strategy_cache(Rules::TripleYahtzee), paths.strategy_cache(Rules::Yahtzee));
+        assert_eq!(paths.old_highscores().unwrap(), Path::new("/home/me/.config/yahtzee.txt"));
+    }
+
+    #[test]
+    fn follows_xdg() {
+        let vars = [("HOME", "/home/me"), ("XDG_DATA_HOME", "/data"), ("XDG_CONFIG_HOME", "relative/is/ignored")];
+        let paths = resolve(&vars, None).unwrap();
+        assert_eq!(paths.highscores(), Path::new("/data/yahtzee/highscores.json"));
+        assert_eq!(paths.ratings(), Path::new("/data/yahtzee/ratings.json"));
+        assert_eq!(paths.config, Path::new("/home/me/.config/yahtzee"));
+    }
+
+    #[test]
+    fn data_dir_without_home() {
+        assert!(resolve(&[("HOME", "")], None).is_err());
+        let paths = resolve(&[], Some("/tmp/y")).unwrap();
+        assert_eq!(paths.saved_game(), Path::new("/tmp/y/save.json"));
+        assert_eq!(paths.cache, Path::new("/tmp/y"));
+        assert_eq!(paths.old_highscores(), None);
+    }
+}
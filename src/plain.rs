@@ -0,0 +1,278 @@
+//! `--plain`: the game as lines of text on stdin and stdout, for dumb
+//! terminals, CI logs and scripts where ncurses can't draw.
+
+use std::io::{self, Write};
+
+use rand::Rng;
+use yahtzee::*;
+
+use crate::table::{add_highscores, box_name, dice_string, load_highscores, record_stats, replay_path, roll_progress,
+                   LocalTable};
+
+const COMMANDS: [&str; 7] = [
+    "Commands:",
+    "    hold 1 3 5   hold exactly these dice, 'hold' alone holds none",
+    "    roll         reroll the dice that aren't held",
+    "    score NAME   score the dice, e.g. 'score fullhouse' or 'score large'.",
+    "                 With several columns add the column: 'score chance 2'",
+    "    card         show the scorecard with what the dice would score",
+    "    quit         leave the game",
+];
+
+/// Prints the prompt and reads an answer, quitting at the end of the input
+fn ask(prompt: &str) -> String {
+    print!("{}", prompt);
+    let _ = io::stdout().flush();
+    let mut line = String::new();
+    match io::stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => {
+            eprintln!("\nEnd of the input, quitting.");
+            std::process::exit(1);
+        }
+        Ok(_) => line.trim().to_string(),
+    }
+}
+
+fn choose_rules() -> Rules {
+    let names: Vec<&str> = Rules::ALL.iter().map(Rules::name).collect();
+    loop {
+        let answer = ask(&format!("Rules ({}) [{}]: ", names.join(", "), Rules::default()));
+        if answer.is_empty() {
+            return Rules::default();
+        }
+        match Rules::from_name(&answer) {
+            Some(rules) => return rules,
+            None => println!("There are no rules called {}", answer),
+        }
+    }
+}
+
+fn choose_difficulty(has_solver: bool) -> Difficulty {
+    let names: Vec<&str> = Difficulty::ALL.into_iter()
+        .filter(|&d| d != Difficulty::Optimal || has_solver)
+        .map(|d| d.name())
+        .collect();
+    loop {
+        let answer = ask(&format!("AI difficulty ({}) [{}]: ", names.join(", "), Difficulty::Heuristic));
+        if answer.is_empty() {
+            return Difficulty::Heuristic;
+        }
+        match Difficulty::from_name(&answer).filter(|d| names.contains(&d.name())) {
+            Some(difficulty) => return difficulty,
+            None => println!("There's no {} AI here", answer),
+        }
+    }
+}
+
+fn choose_players() -> Vec<Player> {
+    let first_name = std::env::var("USER").unwrap_or("Player 1".to_string());
+    loop {
+        let answer = ask(&format!("Players, separated by commas, 'AI' for an AI [{}, AI]: ", first_name));
+        if answer.is_empty() {
+            return vec![Player::new(&first_name, PlayerKind::Human), Player::new("AI", PlayerKind::AI)];
+        }
+        let names: Vec<&str> = answer.split(',').map(str::trim).filter(|name| !name.is_empty()).collect();
+        if names.is_empty() || names.len() > MAX_PLAYERS {
+            println!("1 to {} players can play", MAX_PLAYERS);
+            continue;
+        }
+        let ais = names.iter().filter(|name| name.eq_ignore_ascii_case("ai")).count();
+        let mut ai_number = 0;
+        return names.iter()
+            .map(|name| if name.eq_ignore_ascii_case("ai") {
+                ai_number += 1;
+                let name = if ais > 1 { format!("AI {}", ai_number) } else { "AI".to_string() };
+                Player::new(&name, PlayerKind::AI)
+            } else {
+                Player::new(name, PlayerKind::Human)
+            })
+            .collect();
+    }
+}
+
+/// Lowercase letters and digits only, so that "Full House" is "fullhouse"
+fn simplify(name: &str) -> String {
+    name.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_lowercase()).collect()
+}
+
+/// The box named by the words of a `score` command: a combination, by its name
+/// on the scorecard or the start of it, and the column if there are several
+fn find_box<'a>(rules: Rules, mut words: impl Iterator<Item = &'a str>) -> Result<usize, String> {
+    let name = simplify(words.next().ok_or("Score what? 'card' lists the combinations")?);
+    let names: Vec<[String; 2]> = (0..rules.combinations().len())
+        .map(|i| [simplify(rules.combination_name(i)), simplify(&format!("{:?}", rules.combination(i)))])
+        .collect();
+    let exact = names.iter().position(|names| names.contains(&name));
+    let starting: Vec<usize> = (0..names.len()).filter(|&i| names[i].iter().any(|n| n.starts_with(&name))).collect();
+    let combination = match (exact, &starting[..]) {
+        (Some(i), _) | (None, &[i]) => i,
+        (None, []) => return Err(format!("There's no combination called {}", name)),
+        (None, _) => return Err(format!("{} could be several combinations, say which one", name)),
+    };
+
+    let column = match (words.next(), rules.columns()) {
+        (None, 1) => 0,
+        (None, columns) => return Err(format!("Which column, 1 to {}?", columns)),
+        (Some(column), columns) => match column.parse::<usize>() {
+            Ok(column) if (1..=columns).contains(&column) => column - 1,
+            _ => return Err(format!("The columns are 1 to {}", columns)),
+        },
+    };
+    Ok(column * rules.combinations().len() + combination)
+}
+
+fn print_dice(game_state: &GameState) {
+    let numbers: Vec<String> = (1..=game_state.dice().len()).map(|i| format!(" {} ", i)).collect();
+    println!("    die  {}", numbers.join(" "));
+    println!("    roll {}", dice_string(game_state.dice(), game_state.held()));
+}
+
+/// The current player's scorecard, the free boxes with what the dice would score there
+fn print_card(game_state: &GameState) {
+    let player = game_state.current_player();
+    let data = &player.data;
+    let rules = game_state.rules();
+    let scores = game_state.scores();
+    println!("Scorecard of {}, {} points:", player.name, data.score);
+    for (index, score) in scores.iter().enumerate() {
+        let points = if data.has_used(index) {
+            format!("{:>4}", data.combinations_scores[index])
+        } else if game_state.can_score(index) {
+            format!("   -   would score {}", score)
+        } else {
+            "   -".to_string()
+        };
+        println!("    {:<28}{}", box_name(rules, index), points);
+        if rules.combination(index) == Combinations::Sixes {
+            let column = rules.column(index);
+            let bonus = if data.has_upper_bonus(column) { data.upper_bonus() } else { 0 };
+            println!("    {:<28}{:>4} of {}, bonus {}", "Upper total", data.upper_sum(column), data.upper_threshold(), bonus);
+        }
+    }
+}
+
+fn human_turn(table: &mut LocalTable) {
+    let game_state = table.game();
+    let name = game_state.current_player().name.clone();
+    let rules = game_state.rules();
+    let dice_count = rules.dice();
+    println!("\n{}'s turn {} of {}, {} points", name, game_state.turn.n, rules.turns(),
+             game_state.current_player().data.score);
+    println!("Rolled ({}):", roll_progress(game_state));
+    print_dice(game_state);
+
+    loop {
+        let line = ask("> ");
+        let mut words = line.split_whitespace();
+        match words.next() {
+            None => (),
+            Some("hold") => {
+                let dice: Result<Vec<usize>, _> = words.map(str::parse::<usize>).collect();
+                match dice {
+                    Ok(dice) if dice.iter().all(|d| (1..=dice_count).contains(d)) => {
+                        match (0..dice_count).try_for_each(|i| table.hold(i, dice.contains(&(i + 1)))) {
+                            Ok(()) => print_dice(table.game()),
+                            Err(e) => println!("You can't hold: {}", e),
+                        }
+                    }
+                    _ => println!("The dice are numbered 1 to {}", dice_count),
+                }
+            }
+            Some("roll" | "reroll") => match table.reroll() {
+                Ok(()) => {
+                    println!("Rolled ({}):", roll_progress(table.game()));
+                    print_dice(table.game());
+                }
+                Err(e) => println!("You can't roll: {}", e),
+            },
+            Some("score") => match find_box(rules, words) {
+                Ok(index) => match table.score(index) {
+                    Ok(points) => {
+                        println!("{} scored {} for {} points", name, box_name(rules, index), points);
+                        return;
+                    }
+                    Err(_) => println!("{} can't be scored now, 'card' shows the free boxes", box_name(rules, index)),
+                },
+                Err(e) => println!("{}", e),
+            },
+            Some("card") => print_card(table.game()),
+            Some("help") => println!("{}", COMMANDS.join("\n")),
+            Some("quit") => std::process::exit(0),
+            Some(command) => println!("Unknown command {}, 'help' lists them", command),
+        }
+    }
+}
+
+fn ai_turn(table: &mut LocalTable, strategy: &dyn Strategy) {
+    let seat = table.game().turn.player;
+    let name = table.game().players[seat].name.clone();
+    let choice = play_turn_with(strategy, table, |table| {
+        let game_state = table.game();
+        println!("{} rolled ({}): {}", name, roll_progress(game_state), dice_string(game_state.dice(), game_state.held()));
+    });
+    let points = table.game().players[seat].data.combinations_scores[choice];
+    println!("{} scored {} for {} points", name, box_name(table.game().rules(), choice), points);
+}
+
+/// Prints the results and adds the humans' scores to the stats and the highscores
+fn endgame(paths: &Paths, game_state: &GameState, difficulty: Difficulty) {
+    println!("\nGame over!");
+    for (place, player) in game_state.standings() {
+        println!("{}. {}: {}", place, player.name, player.data.score);
+    }
+    println!("Seed: {}", game_state.seed());
+
+    let seats: Vec<usize> = (0..game_state.players.len()).collect();
+    let humans: Vec<&Player> = game_state.players.iter().filter(|p| p.kind == PlayerKind::Human).collect();
+    if humans.is_empty() {
+        return;
+    }
+    if let Err(e) = record_stats(paths, game_state, difficulty, &seats) {
+        println!("Couldn't update the stats: {}", e);
+    }
+    let (highscores, new_best) = match load_highscores(paths, game_state, &humans) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            println!("Couldn't read the highscores: {}", e);
+            return;
+        }
+    };
+    if new_best {
+        println!("New highscore!");
+    }
+    if let Err(e) = add_highscores(paths, highscores, game_state, difficulty, &humans) {
+        println!("Couldn't save the highscores: {}", e);
+    }
+}
+
+/// Plays a local game on stdin and stdout
+pub fn play(paths: &Paths, rules: Option<Rules>, house_rules: HouseRules, seed: Option<u64>) {
+    println!("Hello, this is Yahtzee. Type 'help' during your turn for the commands.");
+    if !house_rules.is_empty() {
+        print!("House rules:\n{}", house_rules);
+    }
+    let rules = rules.unwrap_or_else(choose_rules);
+    let players = choose_players();
+    let has_solver = rules.has_solver() && house_rules.is_empty();
+    let difficulty = if players.iter().any(|p| p.kind == PlayerKind::AI) {
+        choose_difficulty(has_solver)
+    } else {
+        Difficulty::Heuristic
+    };
+    let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let game_state = GameState::with_house_rules(players, rules, house_rules, seed);
+    let mut table = LocalTable { game_state, difficulty, recorder: None, show_scorecards: false };
+    table.recorder = Recorder::create(&replay_path(paths), &table.game_state, difficulty).ok();
+
+    if difficulty == Difficulty::Optimal {
+        println!("Preparing the optimal AI, the first time this takes a minute...");
+    }
+    let strategy = difficulty.strategy(rules, table.game().house_rules(), &paths.strategy_cache(rules), seed);
+    while !table.game().is_over() {
+        match table.game().current_player().kind {
+            PlayerKind::Human => human_turn(&mut table),
+            PlayerKind::AI => ai_turn(&mut table, strategy.as_ref()),
+        }
+    }
+    endgame(paths, table.game(), difficulty);
+}
@@ -0,0 +1,110 @@
+//! Elo ratings of the tournament players for each ruleset, kept as versioned JSON.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::files::{load_versioned, write_atomically};
+use crate::rules::Rules;
+
+/// Version of the ratings file written by this version of the game
+pub const VERSION: u32 = 1;
+
+/// Rating of a player before their first game
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// How far a single game moves the ratings
+pub const K: f64 = 16.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    pub rating: f64,
+    pub games: u32,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating { rating: INITIAL_RATING, games: 0 }
+    }
+}
+
+/// Expected result of a game for a player rated `a` against one rated `b`:
+/// 1 is a sure win, 0 a sure loss
+pub fn expected(a: f64, b: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((b - a) / 400.0))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ratings {
+    pub version: u32,
+    /// By the rules of the games, then by player name
+    pub rulesets: BTreeMap<Rules, BTreeMap<String, Rating>>,
+}
+
+impl Default for Ratings {
+    fn default() -> Self {
+        Ratings { version: VERSION, rulesets: BTreeMap::new() }
+    }
+}
+
+impl Ratings {
+    /// Reads the ratings, which are empty until the first tournament
+    pub fn load(path: &Path) -> io::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        load_versioned(path, VERSION, |ratings: &Ratings| ratings.version)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_atomically(path, |writer| Ok(serde_json::to_writer(writer, self)?))
+    }
+
+    pub fn get(&self, rules: Rules, name: &str) -> Rating {
+        self.rulesets.get(&rules).and_then(|players| players.get(name)).copied().unwrap_or_default()
+    }
+
+    /// Adds a game between `a` and `b`, `result` being 1 if `a` won, 0.5 for a tie and 0 if `b` won
+    pub fn record_game(&mut self, rules: Rules, a: &str, b: &str, result: f64) {
+        let (rating_a, rating_b) = (self.get(rules, a), self.get(rules, b));
+        let change = K * (result - expected(rating_a.rating, rating_b.rating));
+        let players = self.rulesets.entry(rules).or_default();
+        players.insert(a.to_string(), Rating { rating: rating_a.rating + change, games: rating_a.games + 1 });
+        players.insert(b.to_string(), Rating { rating: rating_b.rating - change, games: rating_b.games + 1 });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn games_move_ratings() {
+        let mut ratings = Ratings::default();
+        assert_eq!(expected(1500.0, 1500.0), 0.5);
+        ratings.record_game(Rules::Yahtzee, "optimal", "greedy", 1.0);
+        assert_eq!(ratings.get(Rules::Yahtzee, "optimal").rating, INITIAL_RATING + K / 2.0);
+        assert_eq!(ratings.get(Rules::Yahtzee, "greedy").rating, INITIAL_RATING - K / 2.0);
+
+        // The favourite gains less for beating the same player again
+        let before = ratings.get(Rules::Yahtzee, "optimal").rating;
+        ratings.record_game(Rules::Yahtzee, "optimal", "greedy", 1.0);
+        let win = ratings.get(Rules::Yahtzee, "optimal").rating - before;
+        assert!(win > 0.0 && win < K / 2.0);
+        assert_eq!(ratings.get(Rules::Yahtzee, "greedy").games, 2);
+        assert_eq!(ratings.get(Rules::Yatzy, "greedy"), Rating::default());
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let path = std::env::temp_dir().join(format!("yahtzee-ratings-test-{}", std::process::id()));
+        let mut ratings = Ratings::default();
+        ratings.record_game(Rules::Yatzy, "a", "./bot", 0.5);
+        ratings.save(&path).unwrap();
+        let loaded = Ratings::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.rulesets, ratings.rulesets);
+    }
+}
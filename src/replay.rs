@@ -0,0 +1,366 @@
+//! Replays of games: every roll, hold and scored combination, one JSON object
+//! per line.
+//!
+//! A replay starts with the whole game as it was when the recording started
+//! (usually its first roll, or later for a continued saved game):
+//!
+//! ```json
+//! {"type":"start","seed":5,"difficulty":"heuristic","turn":{"player":0,"n":1},"players":[...],"dice":[2,5,1,6,5],"held":[false,false,false,false,false],"rolls_left":2}
+//! {"type":"hold","player":0,"held":[false,true,false,false,true]}
+//! {"type":"roll","player":0,"dice":[3,5,4,6,5],"held":[false,true,false,false,true],"rolls_left":1}
+//! {"type":"score","player":0,"combination":10,"points":40}
+//! {"type":"roll","player":1,"dice":[1,1,4,2,6],"held":[false,false,false,false,false],"rolls_left":2}
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::Difficulty;
+use crate::game::{GameError, GameState, Player, Turn};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplayEvent {
+    Start {
+        seed: u64,
+        difficulty: Difficulty,
+        turn: Turn,
+        players: Vec<Player>,
+        dice: Vec<u8>,
+        held: Vec<bool>,
+        rolls_left: u32,
+    },
+    /// The dice held for the next reroll
+    Hold { player: usize, held: Vec<bool> },
+    /// A roll at the start of a turn or a reroll
+    Roll {
+        player: usize,
+        dice: Vec<u8>,
+        held: Vec<bool>,
+        rolls_left: u32,
+    },
+    Score { player: usize, combination: usize, points: u8 },
+    /// The player looked at a hint
+    Hint { player: usize },
+}
+
+/// Makes the moves of a game and writes them to a replay.
+///
+/// A replay that can't be written isn't worth stopping the game for, so the
+/// recording just stops at the first write error.
+pub struct Recorder<W: Write> {
+    writer: Option<W>,
+}
+
+impl Recorder<File> {
+    pub fn create(path: &Path, game_state: &GameState, difficulty: Difficulty) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(Self::new(File::create(path)?, game_state, difficulty))
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// Starts recording the game from its current state
+    pub fn new(writer: W, game_state: &GameState, difficulty: Difficulty) -> Self {
+        let mut recorder = Recorder { writer: Some(writer) };
+        recorder.record(&ReplayEvent::Start {
+            seed: game_state.seed(),
+            difficulty,
+            turn: game_state.turn.clone(),
+            players: game_state.players.clone(),
+            dice: game_state.dice().to_vec(),
+            held: game_state.held().to_vec(),
+            rolls_left: game_state.rolls_left(),
+        });
+        recorder
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    pub fn into_inner(self) -> Option<W> {
+        self.writer
+    }
+
+    fn record(&mut self, event: &ReplayEvent) {
+        if let Some(writer) = &mut self.writer {
+            let line = serde_json::to_string(event).expect("replay events serialize") + "\n";
+            if writer.write_all(line.as_bytes()).and_then(|_| writer.flush()).is_err() {
+                self.writer = None;
+            }
+        }
+    }
+
+    fn record_roll(&mut self, game_state: &GameState) {
+        self.record(&ReplayEvent::Roll {
+            player: game_state.turn.player,
+            dice: game_state.dice().to_vec(),
+            held: game_state.held().to_vec(),
+            rolls_left: game_state.rolls_left(),
+        });
+    }
+
+    /// [`GameState::reroll`], recording the held dice and the new roll
+    pub fn reroll(&mut self, game_state: &mut GameState) -> Result<(), GameError> {
+        let held = game_state.held().to_vec();
+        game_state.reroll()?;
+        self.record(&ReplayEvent::Hold { player: game_state.turn.player, held });
+        self.record_roll(game_state);
+        Ok(())
+    }
+
+    /// [`GameState::use_hint`], recording it
+    pub fn hint(&mut self, game_state: &mut GameState) {
+        game_state.use_hint();
+        self.record(&ReplayEvent::Hint { player: game_state.turn.player });
+    }
+
+    /// [`GameState::score`], recording the choice and the next player's roll
+    pub fn score(&mut self, game_state: &mut GameState, combination: usize) -> Result<u8, GameError> {
+        let player = game_state.turn.player;
+        let points = game_state.score(combination)?;
+        self.record(&ReplayEvent::Score { player, combination, points });
+        if !game_state.is_over() {
+            self.record_roll(game_state);
+        }
+        Ok(points)
+    }
+}
+
+/// A step of a replay and the game right after it
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub event: ReplayEvent,
+    /// After a combination is scored, it's still the turn of the player who
+    /// scored it, with the dice they scored
+    pub game_state: GameState,
+}
+
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub events: Vec<ReplayEvent>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read(reader: impl BufRead) -> io::Result<Self> {
+        let mut events = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                events.push(serde_json::from_str(&line)?);
+            }
+        }
+        check(&events).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Replay { events })
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        match &self.events[0] {
+            ReplayEvent::Start { difficulty, .. } => *difficulty,
+            _ => unreachable!("checked when reading"),
+        }
+    }
+
+    /// Plays the replay through, one frame per event
+    pub fn frames(&self) -> Vec<Frame> {
+        let ReplayEvent::Start { seed, turn, players, dice, held, rolls_left, .. } = &self.events[0] else {
+            unreachable!("checked when reading");
+        };
+        let mut game_state = GameState::restore(turn.clone(), players.clone(), dice.clone(), held.clone(), *rolls_left, *seed);
+        let mut frames = vec![Frame { event: self.events[0].clone(), game_state: game_state.clone() }];
+        let mut turn_over = false;
+
+        for event in &self.events[1..] {
+            match event {
+                ReplayEvent::Start { .. } => continue,
+                ReplayEvent::Hold { held, .. } => {
+                    for (i, &h) in held.iter().enumerate() {
+                        game_state.hold(i, h);
+                    }
+                }
+                ReplayEvent::Roll { dice, held, rolls_left, .. } => {
+                    let mut turn = game_state.turn.clone();
+                    let mut players = game_state.players;
+                    if turn_over {
+                        turn.next(players.len());
+                        // The roll of the new turn includes the player's saved rerolls
+                        players[turn.player].saved_rolls = 0;
+                        turn_over = false;
+                    }
+                    game_state = GameState::restore(turn, players, dice.clone(), held.clone(), *rolls_left, *seed);
+                }
+                ReplayEvent::Score { player, combination, .. } => {
+                    let dice = game_state.dice().to_vec();
+                    let rolls_left = game_state.rolls_left();
+                    let player = &mut game_state.players[*player];
+                    player.data.add_score(*combination, &dice);
+                    if player.data.rules.saves_rolls() {
+                        player.saved_rolls += rolls_left;
+                    }
+                    turn_over = true;
+                }
+                ReplayEvent::Hint { player } => {
+                    game_state.players[*player].hints += 1;
+                }
+            }
+            frames.push(Frame { event: event.clone(), game_state: game_state.clone() });
+        }
+        frames
+    }
+}
+
+/// Checks that a replay from a file can be played through: that it starts
+/// with the game, and that its players, dice and boxes exist in it
+fn check(events: &[ReplayEvent]) -> Result<(), String> {
+    let Some(ReplayEvent::Start { turn, players, dice, held, .. }) = events.first() else {
+        return Err("a replay has to begin with its start".to_string());
+    };
+    let Some(rules) = players.first().map(|p| p.data.rules) else {
+        return Err("a replay needs players".to_string());
+    };
+    let scorecards = players.iter().all(|p| {
+        p.data.rules == rules
+            && p.data.combinations_scores.len() == rules.scorecard_len()
+            && p.data.combinations_used.len() == rules.scorecard_len()
+    });
+    if !scorecards {
+        return Err(format!("the players need {} scorecards", rules));
+    }
+    let check_player = |player: usize| {
+        if player >= players.len() {
+            return Err(format!("there is no player {}", player));
+        }
+        Ok(())
+    };
+    let check_dice = |dice: &[u8], held: &[bool]| {
+        if dice.len() != rules.dice() || held.len() != rules.dice() || !dice.iter().all(|d| (1..=6).contains(d)) {
+            return Err(format!("{} needs {} dice from 1 to 6", rules, rules.dice()));
+        }
+        Ok(())
+    };
+    check_player(turn.player)?;
+    check_dice(dice, held)?;
+    for event in &events[1..] {
+        match event {
+            ReplayEvent::Start { .. } => (),
+            ReplayEvent::Hold { player, held } => {
+                check_player(*player)?;
+                if held.len() != rules.dice() {
+                    return Err(format!("{} needs {} dice held or not", rules, rules.dice()));
+                }
+            }
+            ReplayEvent::Roll { player, dice, held, .. } => {
+                check_player(*player)?;
+                check_dice(dice, held)?;
+            }
+            ReplayEvent::Score { player, combination, .. } => {
+                check_player(*player)?;
+                if *combination >= rules.scorecard_len() {
+                    return Err(format!("there is no box {} in {}", combination, rules));
+                }
+            }
+            ReplayEvent::Hint { player } => check_player(*player)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ai::{GreedyAi, HeuristicAi, Strategy};
+    use crate::game::PlayerKind;
+    use crate::rules::Rules;
+
+    #[test]
+    fn replay_rebuilds_the_game() {
+        let players = vec![Player::new("A", PlayerKind::AI), Player::new("B", PlayerKind::AI)];
+        let mut game_state = GameState::with_seed(players, 3);
+        let mut recorder = Recorder::new(Vec::new(), &game_state, Difficulty::Heuristic);
+
+        while !game_state.is_over() {
+            let player = game_state.current_player().data.clone();
+            let held = HeuristicAi.hold(&player, game_state.dice(), game_state.rolls_left());
+            if game_state.rolls_left() > 0 && !held.iter().all(|&h| h) {
+                for (i, &h) in held.iter().enumerate() {
+                    game_state.hold(i, h);
+                }
+                recorder.reroll(&mut game_state).unwrap();
+                continue;
+            }
+            if game_state.turn.n == 1 {
+                recorder.hint(&mut game_state);
+            }
+            let choice = GreedyAi.choose(&player, game_state.dice());
+            recorder.score(&mut game_state, choice).unwrap();
+        }
+        assert!(recorder.is_recording());
+
+        let replay = Replay::read(&recorder.into_inner().unwrap()[..]).unwrap();
+        assert_eq!(replay.difficulty(), Difficulty::Heuristic);
+        let frames = replay.frames();
+        assert_eq!(frames.len(), replay.events.len());
+        let last = &frames.last().unwrap().game_state;
+        for (replayed, played) in last.players.iter().zip(&game_state.players) {
+            assert_eq!(replayed.data.score, played.data.score);
+            assert_eq!(replayed.data.combinations_scores, played.data.combinations_scores);
+            assert_eq!(replayed.hints, 1);
+
+        }
+        let scores = replay.events.iter().filter(|e| matches!(e, ReplayEvent::Score { .. })).count();
+        assert_eq!(scores, 26);
+    }
+
+    #[test]
+    fn needs_a_start() {
+        let line = r#"{"type":"score","player":0,"combination":1,"points":4}"#;
+        assert!(Replay::read(line.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn rejects_moves_outside_the_game() {
+        let players = vec![Player::new("A", PlayerKind::AI), Player::new("B", PlayerKind::AI)];
+        let game_state = GameState::with_seed(players, 3);
+        let start = Recorder::new(Vec::new(), &game_state, Difficulty::Greedy).into_inner().unwrap();
+        for line in [
+            r#"{"type":"hold","player":0,"held":[true,true,true,true,true,true]}"#,
+            r#"{"type":"hint","player":2}"#,
+            r#"{"type":"roll","player":0,"dice":[1,2,3,4,7],"held":[false,false,false,false,false],"rolls_left":1}"#,
+            r#"{"type":"score","player":0,"combination":13,"points":0}"#,
+        ] {
+            let replay = [&start[..], line.as_bytes()].concat();
+            let error = Replay::read(&replay[..]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", line);
+        }
+    }
+
+    #[test]
+    fn replay_keeps_saved_rolls() {
+        let players = vec![Player::new("A", PlayerKind::AI), Player::new("B", PlayerKind::AI)];
+        let mut game_state = GameState::with_rules(players, Rules::MaxiYatzy, 8);
+        let mut recorder = Recorder::new(Vec::new(), &game_state, Difficulty::Greedy);
+        // A saves both rerolls, B uses them all
+        recorder.score(&mut game_state, 0).unwrap();
+        recorder.reroll(&mut game_state).unwrap();
+        recorder.reroll(&mut game_state).unwrap();
+        recorder.score(&mut game_state, 0).unwrap();
+        recorder.reroll(&mut game_state).unwrap();
+
+        let replay = Replay::read(&recorder.into_inner().unwrap()[..]).unwrap();
+        let last = replay.frames().pop().unwrap().game_state;
+        assert_eq!(last.dice(), game_state.dice());
+        assert_eq!(last.rolls_left(), 3);
+        assert_eq!(last.players[0].saved_rolls, 0);
+        assert_eq!(last.players[1].saved_rolls, 0);
+    }
+}
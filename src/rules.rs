@@ -0,0 +1,230 @@
+//! The rule variants the game can be played by.
+//!
+//! | | Yahtzee | Yatzy |
+//! |---|---|---|
+//! | Combinations | 13 | 15, with One Pair and Two Pairs |
+//! | 3 and 4 of a kind | total of all dice | total of the matching dice |
+//! | Full House | 25 | total of all dice |
+//! | Small Straight | any 4 in a row, 30 | 1-2-3-4-5, 15 |
+//! | Large Straight | any 5 in a row, 40 | 2-3-4-5-6, 20 |
+//! | Upper bonus at 63 | 35 | 50 |
+//! | Extra Yahtzees | 100 point bonus and Joker | nothing |
+//!
+//! Maxi Yatzy is Yatzy with six dice and 20 combinations: it adds Three Pairs,
+//! 5 of a kind, Full Straight (1-6, 21 points), Castle (two triples) and Tower
+//! (four and two of a kind), the last three scoring the total of the dice.
+//! Full House is the best three and two of a kind, the upper bonus is 100 at 84
+//! and Maxi Yatzy, six of a kind, scores 100. Rerolls a player doesn't use are
+//! saved for their later turns.
+//!
+//! Triple Yahtzee is played on three Yahtzee columns at once, for 39 turns.
+//! Every column has its own upper bonus and Jokers, and the totals of the
+//! columns count once, twice and three times. Extra Yahtzee bonuses aren't
+//! multiplied.
+//!
+//! The boxes of a scorecard are numbered column by column: the combinations
+//! of the first column, then those of the second one and so on.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::scoring::Combinations;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rules {
+    /// American Yahtzee
+    #[default]
+    Yahtzee,
+    /// Scandinavian Yatzy
+    Yatzy,
+    /// Three Yahtzee columns counting x1, x2 and x3
+    TripleYahtzee,
+    /// Yatzy with six dice and saved rerolls
+    MaxiYatzy,
+}
+
+impl Rules {
+    pub const ALL: [Rules; 4] = [Rules::Yahtzee, Rules::Yatzy, Rules::TripleYahtzee, Rules::MaxiYatzy];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rules::Yahtzee => "yahtzee",
+            Rules::Yatzy => "yatzy",
+            Rules::TripleYahtzee => "triple",
+            Rules::MaxiYatzy => "maxi",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.name() == name)
+    }
+
+    /// The name of the game by these rules
+    pub fn title(&self) -> &'static str {
+        match self {
+            Rules::Yahtzee => "Yahtzee",
+            Rules::Yatzy => "Yatzy",
+            Rules::TripleYahtzee => "Triple Yahtzee",
+            Rules::MaxiYatzy => "Maxi Yatzy",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Rules::Yahtzee => "American rules, 13 combinations and Yahtzee bonuses",
+            Rules::Yatzy => "Scandinavian rules, 15 combinations with pairs",
+            Rules::TripleYahtzee => "three Yahtzee columns worth x1, x2 and x3",
+            Rules::MaxiYatzy => "six dice, 20 combinations and saved rerolls",
+        }
+    }
+
+    /// Number of dice rolled
+    pub fn dice(&self) -> usize {
+        match self {
+            Rules::MaxiYatzy => 6,
+            _ => 5,
+        }
+    }
+
+    /// Whether the rerolls left at the end of a turn can be used in the player's later turns
+    pub fn saves_rolls(&self) -> bool {
+        *self == Rules::MaxiYatzy
+    }
+
+    /// Whether the optimal AI (and so the hints and the analysis) can play by
+    /// these rules. Six dice and saved rerolls make far too many states.
+    pub fn has_solver(&self) -> bool {
+        self.column_rules() != Rules::MaxiYatzy
+    }
+
+    /// The combinations of a column of the scorecard, in order
+    pub fn combinations(&self) -> &'static [Combinations] {
+        use Combinations::*;
+        match self {
+            Rules::Yahtzee | Rules::TripleYahtzee => &[
+                Aces, Twos, Threes, Fours, Fives, Sixes,
+                ThreeOfAKind, FourOfAKind, FullHouse, SmallStraight, LargeStraight, Yahtzee, Chance,
+            ],
+            Rules::Yatzy => &[
+                Aces, Twos, Threes, Fours, Fives, Sixes,
+                OnePair, TwoPairs, ThreeOfAKind, FourOfAKind, SmallStraight, LargeStraight, FullHouse, Chance, Yahtzee,
+            ],
+            Rules::MaxiYatzy => &[
+                Aces, Twos, Threes, Fours, Fives, Sixes,
+                OnePair, TwoPairs, ThreePairs, ThreeOfAKind, FourOfAKind, FiveOfAKind,
+                SmallStraight, LargeStraight, FullStraight, FullHouse, Castle, Tower, Chance, Yahtzee,
+            ],
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        match self {
+            Rules::TripleYahtzee => 3,
+            _ => 1,
+        }
+    }
+
+    /// How many times the total of the column counts
+    pub fn column_weight(&self, column: usize) -> i32 {
+        column as i32 + 1
+    }
+
+    /// The rules of a single column of the scorecard
+    pub fn column_rules(&self) -> Rules {
+        match self {
+            Rules::TripleYahtzee => Rules::Yahtzee,
+            _ => *self,
+        }
+    }
+
+    /// Number of boxes on the scorecard
+    pub fn scorecard_len(&self) -> usize {
+        self.combinations().len() * self.columns()
+    }
+
+    /// Column of the box at `index`
+    pub fn column(&self, index: usize) -> usize {
+        index / self.combinations().len()
+    }
+
+    /// Combination of the box at `index`
+    pub fn combination(&self, index: usize) -> Combinations {
+        self.combinations()[index % self.combinations().len()]
+    }
+
+    /// Index of the combination in the first column, if it's played
+    pub fn index(&self, combination: Combinations) -> Option<usize> {
+        self.combinations().iter().position(|&c| c == combination)
+    }
+
+    /// Indices of the combination in every column
+    pub fn indices(&self, combination: Combinations) -> impl Iterator<Item = usize> {
+        let len = self.combinations().len();
+        let index = self.index(combination);
+        (0..self.columns()).filter_map(move |column| index.map(|i| column * len + i))
+    }
+
+    /// Name of the combination of the box at `index`
+    pub fn combination_name(&self, index: usize) -> &'static str {
+        use Combinations::*;
+        match (self.column_rules(), self.combination(index)) {
+            (_, Aces) => "Aces",
+            (_, Twos) => "Twos",
+            (_, Threes) => "Threes",
+            (_, Fours) => "Fours",
+            (_, Fives) => "Fives",
+            (_, Sixes) => "Sixes",
+            (_, OnePair) => "One Pair",
+            (_, TwoPairs) => "Two Pairs",
+            (_, ThreePairs) => "Three Pairs",
+            (_, ThreeOfAKind) => "3 of a kind",
+            (_, FourOfAKind) => "4 of a kind",
+            (_, FiveOfAKind) => "5 of a kind",
+            (_, FullHouse) => "Full House",
+            (_, Castle) => "Castle",
+            (_, Tower) => "Tower",
+            (_, SmallStraight) => "Small Straight",
+            (_, LargeStraight) => "Large Straight",
+            (_, FullStraight) => "Full Straight",
+            (Rules::Yatzy, Yahtzee) => "Yatzy",
+            (Rules::MaxiYatzy, Yahtzee) => "Maxi Yatzy",
+            (_, Yahtzee) => "Yahtzee (5 of a kind)",
+            (_, Chance) => "Chance",
+        }
+    }
+
+    /// A game lasts a turn per box of the scorecard
+    pub fn turns(&self) -> u32 {
+        self.scorecard_len() as u32
+    }
+
+    /// Upper section sum of a column that earns the bonus
+    pub fn upper_threshold(&self) -> i32 {
+        match self.column_rules() {
+            Rules::MaxiYatzy => 84,
+            _ => 63,
+        }
+    }
+
+    /// Points for reaching the threshold in the upper section of a column
+    pub fn upper_bonus(&self) -> u8 {
+        match self.column_rules() {
+            Rules::Yatzy => 50,
+            Rules::MaxiYatzy => 100,
+            _ => 35,
+        }
+    }
+
+    /// Whether Yahtzees after the first in a column score a 100 point bonus and are Jokers
+    pub fn has_jokers(&self) -> bool {
+        self.column_rules() == Rules::Yahtzee
+    }
+}
+
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
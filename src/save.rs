@@ -0,0 +1,59 @@
+//! Games saved when quitting, to be continued later.
+
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::Difficulty;
+use crate::files::write_atomically;
+use crate::game::GameState;
+
+/// A local game in progress, including the dice of the current turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedGame {
+    /// Difficulty of the AI players
+    pub difficulty: Difficulty,
+    pub game_state: GameState,
+}
+
+impl SavedGame {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Writes the game to `path`, replacing an older save only once it's written
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_atomically(path, |writer| Ok(serde_json::to_writer(writer, self)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn save_and_load() {
+        let mut game_state = GameState::default();
+        game_state.score(12).unwrap();
+        game_state.hold(3, true);
+        game_state.reroll().unwrap();
+        let saved = SavedGame { difficulty: Difficulty::Optimal, game_state };
+
+        let path = std::env::temp_dir().join(format!("yahtzee-save-test-{}.json", std::process::id()));
+        saved.save(&path).unwrap();
+        let loaded = SavedGame::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.difficulty, Difficulty::Optimal);
+        let (a, b) = (&saved.game_state, &loaded.game_state);
+        assert_eq!((a.turn.player, a.turn.n), (b.turn.player, b.turn.n));
+        assert_eq!(a.dice(), b.dice());
+        assert_eq!(a.held(), b.held());
+        assert_eq!(a.rolls_left(), b.rolls_left());
+        assert_eq!(b.players[0].data.combinations_scores, a.players[0].data.combinations_scores);
+        assert!(b.players[0].data.has_used(12));
+    }
+}
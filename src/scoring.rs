@@ -0,0 +1,478 @@
+use serde::{Deserialize, Serialize};
+
+use crate::house_rules::HouseRules;
+use crate::rules::Rules;
+
+/// Every combination of every ruleset. The discriminants are the indices on a
+/// Yahtzee scorecard, [`Rules::index`] finds them on the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combinations {
+    Aces             = 0,
+    Twos             = 1,
+    Threes           = 2,
+    Fours            = 3,
+    Fives            = 4,
+    Sixes            = 5,
+    ThreeOfAKind     = 6,
+    FourOfAKind      = 7,
+    FullHouse        = 8,
+    SmallStraight    = 9,
+    LargeStraight    = 10,
+    Yahtzee          = 11,
+    Chance           = 12,
+    OnePair          = 13,
+    TwoPairs         = 14,
+    ThreePairs       = 15,
+    FiveOfAKind      = 16,
+    FullStraight     = 17,
+    Castle           = 18,
+    Tower            = 19,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerData {
+    /// Scorecards from before there were other rules are Yahtzee ones
+    #[serde(default)]
+    pub rules: Rules,
+    pub score: i32,
+    /// By box, column after column, see [`Rules`]
+    pub combinations_scores: Vec<u8>,
+    pub combinations_used: Vec<bool>,
+    /// Whether the upper bonus was scored, in any column
+    pub got_upper_bonus: bool,
+    pub yahtzee_bonuses: u32,
+    /// By column, whether the Yahtzee box holds a Yahtzee rather than a zero,
+    /// as house rules can make one worth no points. Empty on older scorecards.
+    #[serde(default)]
+    pub scored_yahtzee: Vec<bool>,
+    #[serde(default, skip_serializing_if = "HouseRules::is_empty")]
+    pub house_rules: HouseRules,
+}
+
+impl Default for PlayerData {
+    /// An empty Yahtzee scorecard
+    fn default() -> Self {
+        Self::new(Rules::default())
+    }
+}
+
+impl PlayerData {
+    pub fn new(rules: Rules) -> Self {
+        Self::with_house_rules(rules, HouseRules::default())
+    }
+    pub fn with_house_rules(rules: Rules, house_rules: HouseRules) -> Self {
+        let boxes = rules.scorecard_len();
+        PlayerData {
+            rules,
+            score: 0,
+            combinations_scores: vec![0; boxes],
+            combinations_used: vec![false; boxes],
+            got_upper_bonus: false,
+            yahtzee_bonuses: 0,
+            scored_yahtzee: vec![false; rules.columns()],
+            house_rules,
+        }
+    }
+    pub fn has_used(&self, index: usize) -> bool {
+        self.combinations_used[index]
+    }
+    /// Index of the first box of the column
+    fn column_start(&self, column: usize) -> usize {
+        column * self.rules.combinations().len()
+    }
+    pub fn upper_sum(&self, column: usize) -> i32 {
+        let start = self.column_start(column);
+        self.combinations_scores[start..start + 6].iter().map(|&s| s as i32).sum()
+    }
+    /// Upper section sum of a column that earns the bonus, by the rules and the house rules
+    pub fn upper_threshold(&self) -> i32 {
+        self.house_rules.upper_threshold(self.rules)
+    }
+    pub fn upper_bonus(&self) -> u8 {
+        self.house_rules.upper_bonus(self.rules)
+    }
+    pub fn has_upper_bonus(&self, column: usize) -> bool {
+        self.upper_sum(column) >= self.upper_threshold()
+    }
+    /// Sum of the column's lower section, without the Yahtzee bonuses
+    pub fn lower_sum(&self, column: usize) -> i32 {
+        let start = self.column_start(column);
+        let end = start + self.rules.combinations().len();
+        self.combinations_scores[start + 6..end].iter().map(|&s| s as i32).sum()
+    }
+    /// Total of the column with its upper bonus, before it's multiplied
+    pub fn column_total(&self, column: usize) -> i32 {
+        let bonus = if self.has_upper_bonus(column) { self.upper_bonus() as i32 } else { 0 };
+        self.upper_sum(column) + self.lower_sum(column) + bonus
+    }
+    fn yahtzee_index(&self, column: usize) -> usize {
+        self.column_start(column) + self.rules.index(Combinations::Yahtzee).expect("every ruleset has a Yahtzee")
+    }
+    /// Whether the Yahtzee box of the column was filled with a Yahtzee, which
+    /// makes the later ones earn bonuses
+    pub fn has_scored_yahtzee(&self, column: usize) -> bool {
+        match self.scored_yahtzee.get(column) {
+            Some(&scored) => scored,
+            // Older scorecards only have the points
+            None => self.combinations_scores[self.yahtzee_index(column)] > 0,
+        }
+    }
+    /// A Yahtzee rolled after the Yahtzee box of the column has been filled (with 50 or 0) is a Joker there
+    pub fn is_joker(&self, column: usize, dice: &[u8]) -> bool {
+        self.rules.has_jokers() && is_yahtzee(dice) && self.has_used(self.yahtzee_index(column))
+    }
+    /// Forced Joker rules: the matching upper box must be used if it's free,
+    /// otherwise any free lower box, and only if those are all filled any upper box
+    pub fn can_use(&self, index: usize, dice: &[u8]) -> bool {
+        if self.has_used(index) {
+            return false;
+        }
+        let column = self.rules.column(index);
+        if !self.is_joker(column, dice) {
+            return true;
+        }
+        let start = self.column_start(column);
+        let upper = start + dice[0] as usize - 1;
+        if !self.has_used(upper) {
+            return index == upper;
+        }
+        let lower = start + 6..start + self.rules.combinations().len();
+        if lower.clone().any(|i| !self.has_used(i)) {
+            lower.contains(&index)
+        } else {
+            true
+        }
+    }
+    pub fn add_score(&mut self, index: usize, dice: &[u8]) {
+        let column = self.rules.column(index);
+        let weight = self.rules.column_weight(column);
+        let score = calculate_scores(dice, self)[index];
+        if self.is_joker(column, dice) && self.has_scored_yahtzee(column) && self.house_rules.yahtzee_bonus() {
+            self.score += 100;
+            self.yahtzee_bonuses += 1;
+        }
+        if index == self.yahtzee_index(column) && is_yahtzee(dice) {
+            if let Some(scored) = self.scored_yahtzee.get_mut(column) {
+                *scored = true;
+            }
+        }
+        let had_upper_bonus = self.has_upper_bonus(column);
+        self.combinations_scores[index] = score;
+        self.combinations_used[index] = true;
+        self.score += weight * score as i32;
+        if !had_upper_bonus && self.has_upper_bonus(column) {
+            self.score += weight * self.upper_bonus() as i32;
+            self.got_upper_bonus = true;
+        }
+    }
+    pub fn is_complete(&self) -> bool {
+        self.combinations_used.iter().all(|&used| used)
+    }
+}
+
+pub fn is_yahtzee(dice: &[u8]) -> bool {
+    dice.iter().all(|&d| d == dice[0])
+}
+
+/// Points of the dice in a combination by the rules, without Jokers
+pub fn score_combination(rules: Rules, combination: Combinations, dice: &[u8]) -> u8 {
+    use Combinations::*;
+
+    let mut counts = [0u8; 7];
+    for &d in dice {
+        counts[d as usize] += 1;
+    }
+    let total: u8 = dice.iter().sum();
+    // Faces with at least `n` dice, the highest first
+    let faces_with = |n: u8| (1..=6u8).rev().filter(move |&f| counts[f as usize] >= n);
+    let most_frequent_count = *counts.iter().max().unwrap();
+    let full_house = counts.contains(&3) && counts.contains(&2);
+    let straight_len = counts[1..].split(|&c| c == 0).map(|run| run.len()).max().unwrap_or(0);
+    let has_run = |faces: std::ops::RangeInclusive<usize>| faces.into_iter().all(|f| counts[f] > 0);
+    // The highest faces of `n` different pairs
+    let pairs = |n: usize| {
+        let faces: Vec<u8> = faces_with(2).take(n).collect();
+        if faces.len() == n { faces.iter().map(|f| 2 * f).sum::<u8>() } else { 0 }
+    };
+
+    match (rules.column_rules(), combination) {
+        (_, Aces | Twos | Threes | Fours | Fives | Sixes) => {
+            let face = combination as u8 + 1;
+            counts[face as usize] * face
+        }
+        (_, Chance) => total,
+        (Rules::MaxiYatzy, Yahtzee) if most_frequent_count as usize == dice.len() => 100,
+        (_, Yahtzee) if most_frequent_count as usize == dice.len() => 50,
+
+        (Rules::Yahtzee, ThreeOfAKind) if most_frequent_count >= 3 => total,
+        (Rules::Yahtzee, FourOfAKind) if most_frequent_count >= 4 => total,
+        (Rules::Yahtzee, FullHouse) if full_house => 25,
+        (Rules::Yahtzee, SmallStraight) if straight_len >= 4 => 30,
+        (Rules::Yahtzee, LargeStraight) if straight_len >= 5 => 40,
+
+        (Rules::Yatzy | Rules::MaxiYatzy, OnePair) => pairs(1),
+        (Rules::Yatzy | Rules::MaxiYatzy, TwoPairs) => pairs(2),
+        (Rules::MaxiYatzy, ThreePairs) => pairs(3),
+        (Rules::Yatzy | Rules::MaxiYatzy, ThreeOfAKind) => faces_with(3).next().map_or(0, |f| 3 * f),
+        (Rules::Yatzy | Rules::MaxiYatzy, FourOfAKind) => faces_with(4).next().map_or(0, |f| 4 * f),
+        (Rules::MaxiYatzy, FiveOfAKind) => faces_with(5).next().map_or(0, |f| 5 * f),
+        // The highest three of a kind with the highest pair of another face,
+        // which with five dice is all of them
+        (Rules::Yatzy | Rules::MaxiYatzy, FullHouse) => faces_with(3)
+            .find_map(|three| faces_with(2).find(|&two| two != three).map(|two| 3 * three + 2 * two))
+            .unwrap_or(0),
+        (Rules::MaxiYatzy, Castle) if faces_with(3).count() == 2 => total,
+        (Rules::MaxiYatzy, Tower) if counts.contains(&4) && counts.contains(&2) => total,
+        (Rules::Yatzy | Rules::MaxiYatzy, SmallStraight) if has_run(1..=5) => 15,
+        (Rules::Yatzy | Rules::MaxiYatzy, LargeStraight) if has_run(2..=6) => 20,
+        (Rules::MaxiYatzy, FullStraight) if has_run(1..=6) => 21,
+
+        _ => 0,
+    }
+}
+
+/// What the dice would score in each box of the player's scorecard, before
+/// the column is multiplied
+pub fn calculate_scores(dice: &[u8], player: &PlayerData) -> Vec<u8> {
+    let rules = player.rules;
+    let house_rules = &player.house_rules;
+    let column_scores: Vec<u8> = rules.combinations().iter()
+        .map(|&c| house_rules.points(c, score_combination(rules, c, dice)))
+        .collect();
+    let mut scores = column_scores.repeat(rules.columns());
+    for (column, scores) in scores.chunks_mut(column_scores.len()).enumerate() {
+        if player.is_joker(column, dice) {
+            for (combination, points) in [(Combinations::FullHouse, 25), (Combinations::SmallStraight, 30), (Combinations::LargeStraight, 40)] {
+                if let Some(i) = rules.index(combination) {
+                    scores[i] = house_rules.points(combination, points);
+                }
+            }
+        }
+    }
+    scores
+}
+
+/// Name of the combination in the box at `index` on a scorecard of the rules
+pub fn score_index_to_string(rules: Rules, i: usize) -> &'static str {
+    rules.combination_name(i)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn three_of_a_kind() {
+        let dice = [1, 2, 3, 3, 3];
+        let scores = calculate_scores(&dice, &PlayerData::default());
+        assert_eq!(scores, [1u8, 2, 9, 0, 0, 0, 12, 0, 0, 0, 0, 0, 12]);
+    }
+    #[test]
+    fn four_of_a_kind() {
+        let dice = [1, 3, 3, 3, 3];
+        let scores = calculate_scores(&dice, &PlayerData::default());
+        assert_eq!(scores, [1u8, 0, 12, 0, 0, 0, 13, 13, 0, 0, 0, 0, 13]);
+    }
+
+    #[test]
+    fn fullhouse() {
+        let dice = [4, 4, 3, 3, 3];
+        let scores = calculate_scores(&dice, &PlayerData::default());
+        assert_eq!(scores, [0u8, 0, 9, 8, 0, 0, 17, 0, 25, 0, 0, 0, 17]);
+    }
+
+    #[test]
+    fn yahtzee() {
+        let dice = [1, 1, 1, 1, 1];
+        let scores = calculate_scores(&dice, &PlayerData::default());
+        assert_eq!(scores, [5u8, 0, 0, 0, 0, 0, 5, 5, 0, 0, 0, 50, 5]);
+    }
+
+    #[test]
+    fn small_straight() {
+        let dice = [3, 2, 1, 4, 3];
+        let scores = calculate_scores(&dice, &PlayerData::default());
+        assert_eq!(scores, [1u8, 2, 6, 4, 0, 0, 0, 0, 0, 30, 0, 0, 13]);
+    }
+
+    #[test]
+    fn large_straight() {
+        let dice = [3, 2, 1, 4, 5];
+        let scores = calculate_scores(&dice, &PlayerData::default());
+        assert_eq!(scores, [1u8, 2, 3, 4, 5, 0, 0, 0, 0, 30, 40, 0, 15]);
+    }
+
+    #[test]
+    fn yahtzee_bonus() {
+        let mut player = PlayerData::default();
+        player.add_score(Combinations::Yahtzee as usize, &[2, 2, 2, 2, 2]);
+        assert_eq!(player.score, 50);
+        player.add_score(Combinations::Threes as usize, &[3, 3, 3, 3, 3]);
+        assert_eq!(player.score, 50 + 100 + 15);
+        assert_eq!(player.yahtzee_bonuses, 1);
+    }
+
+    #[test]
+    fn no_bonus_after_zeroed_yahtzee() {
+        let mut player = PlayerData::default();
+        player.add_score(Combinations::Yahtzee as usize, &[1, 2, 3, 4, 6]);
+        player.add_score(Combinations::Fours as usize, &[4, 4, 4, 4, 4]);
+        assert_eq!(player.score, 20);
+        assert_eq!(player.yahtzee_bonuses, 0);
+    }
+
+    #[test]
+    fn forced_joker() {
+        let dice = [5, 5, 5, 5, 5];
+        let mut player = PlayerData::default();
+        player.add_score(Combinations::Yahtzee as usize, &dice);
+
+        // The matching upper box has to be taken first
+        assert!(player.can_use(Combinations::Fives as usize, &dice));
+        assert!(!player.can_use(Combinations::FullHouse as usize, &dice));
+
+        player.add_score(Combinations::Fives as usize, &[5, 5, 5, 1, 2]);
+        assert!(!player.can_use(Combinations::Aces as usize, &dice));
+        assert!(player.can_use(Combinations::LargeStraight as usize, &dice));
+
+        let scores = calculate_scores(&dice, &player);
+        assert_eq!(scores, [0u8, 0, 0, 0, 25, 0, 25, 25, 25, 30, 40, 50, 25]);
+    }
+
+    #[test]
+    fn yatzy_scores_matching_dice() {
+        let yatzy = PlayerData::new(Rules::Yatzy);
+        // One Pair, Two Pairs, 3 and 4 of a kind, Small and Large Straight, Full House, Chance, Yatzy
+        assert_eq!(calculate_scores(&[4, 4, 3, 3, 3], &yatzy)[6..], [8u8, 14, 9, 0, 0, 0, 17, 17, 0]);
+        assert_eq!(calculate_scores(&[6, 6, 6, 6, 1], &yatzy)[6..], [12u8, 0, 18, 24, 0, 0, 0, 25, 0]);
+        assert_eq!(calculate_scores(&[3, 2, 1, 4, 5], &yatzy)[6..], [0u8, 0, 0, 0, 15, 0, 0, 15, 0]);
+        assert_eq!(calculate_scores(&[3, 2, 6, 4, 5], &yatzy)[6..], [0u8, 0, 0, 0, 0, 20, 0, 20, 0]);
+        assert_eq!(calculate_scores(&[2, 2, 2, 2, 2], &yatzy)[6..], [4u8, 0, 6, 8, 0, 0, 0, 10, 50]);
+    }
+
+    #[test]
+    fn yatzy_has_no_jokers_or_bonuses() {
+        let mut player = PlayerData::new(Rules::Yatzy);
+        let yatzy = Rules::Yatzy.index(Combinations::Yahtzee).unwrap();
+        player.add_score(yatzy, &[6, 6, 6, 6, 6]);
+        player.add_score(Combinations::Sixes as usize, &[6, 6, 6, 6, 6]);
+        assert!(player.can_use(Combinations::Aces as usize, &[6, 6, 6, 6, 6]));
+        assert_eq!(player.score, 50 + 30);
+        assert_eq!(player.yahtzee_bonuses, 0);
+
+        for (face, dice) in [(1, [1; 5]), (2, [2; 5]), (3, [3; 5]), (4, [4; 5])] {
+            player.add_score(face - 1, &dice);
+        }
+        // 30 + 5 + 10 + 15 + 20 reaches 63 for the Yatzy bonus of 50
+        assert!(player.got_upper_bonus);
+        assert_eq!(player.score, 50 + 80 + 50);
+    }
+
+    #[test]
+    fn triple_columns_are_weighted() {
+        let mut player = PlayerData::new(Rules::TripleYahtzee);
+        let chance = |column: usize| column * 13 + Combinations::Chance as usize;
+        assert_eq!(calculate_scores(&[6, 6, 6, 6, 5], &player).len(), 39);
+        for column in 0..3 {
+            player.add_score(chance(column), &[6, 6, 6, 6, 5]);
+        }
+        assert_eq!(player.score, 29 + 2 * 29 + 3 * 29);
+        assert_eq!(player.column_total(2), 29);
+        assert_eq!(player.lower_sum(2), 29);
+
+        for face in 1..=6u8 {
+            player.add_score(26 + face as usize - 1, &[face, face, face, face, 1]);
+        }
+        assert!(player.has_upper_bonus(2) && !player.has_upper_bonus(0));
+        assert_eq!(player.score, 6 * 29 + 3 * (85 + 35));
+        assert_eq!((player.upper_sum(2), player.lower_sum(2), player.column_total(2)), (85, 29, 85 + 29 + 35));
+    }
+
+    #[test]
+    fn triple_jokers_are_per_column() {
+        let mut player = PlayerData::new(Rules::TripleYahtzee);
+        let yahtzee = Combinations::Yahtzee as usize;
+        player.add_score(13 + yahtzee, &[2; 5]);
+        assert_eq!(player.score, 100);
+
+        // Only the second column has a Yahtzee, the others are free as usual
+        assert!(player.can_use(Combinations::Chance as usize, &[2; 5]));
+        assert!(!player.can_use(13 + Combinations::Chance as usize, &[2; 5]));
+        assert!(player.can_use(13 + Combinations::Twos as usize, &[2; 5]));
+        player.add_score(13 + Combinations::Twos as usize, &[2; 5]);
+        assert_eq!(player.score, 100 + 100 + 2 * 10);
+        player.add_score(yahtzee, &[2; 5]);
+        assert_eq!((player.score, player.yahtzee_bonuses), (100 + 100 + 20 + 50, 1));
+    }
+    #[test]
+    fn maxi_scores_six_dice() {
+        let maxi = PlayerData::new(Rules::MaxiYatzy);
+        let score = |dice: &[u8], combination| calculate_scores(dice, &maxi)[Rules::MaxiYatzy.index(combination).unwrap()];
+        assert_eq!(score(&[1, 1, 3, 3, 5, 5], Combinations::ThreePairs), 18);
+        assert_eq!(score(&[3, 3, 3, 3, 5, 5], Combinations::TwoPairs), 16);
+        assert_eq!(score(&[6, 6, 6, 5, 5, 5], Combinations::FullHouse), 28);
+        assert_eq!(score(&[6, 6, 6, 5, 5, 5], Combinations::Castle), 33);
+        assert_eq!(score(&[2, 2, 2, 2, 6, 6], Combinations::Tower), 20);
+        assert_eq!(score(&[4, 4, 4, 4, 4, 1], Combinations::FiveOfAKind), 20);
+        assert_eq!(score(&[4, 4, 4, 4, 4, 1], Combinations::Tower), 0);
+        assert_eq!(score(&[1, 2, 3, 4, 5, 6], Combinations::FullStraight), 21);
+        assert_eq!(score(&[1, 2, 3, 4, 5, 6], Combinations::LargeStraight), 20);
+        assert_eq!(score(&[2; 6], Combinations::Yahtzee), 100);
+        assert_eq!(score(&[2, 2, 2, 2, 2, 1], Combinations::Yahtzee), 0);
+    }
+
+    #[test]
+    fn maxi_upper_bonus_at_84() {
+        let mut player = PlayerData::new(Rules::MaxiYatzy);
+        for face in 1..=5u8 {
+            player.add_score(face as usize - 1, &[face, face, face, face, 1, 1]);
+        }
+        assert_eq!(player.upper_sum(0), 62);
+        assert!(!player.got_upper_bonus);
+        // Four of every face is exactly 84
+        player.add_score(Combinations::Sixes as usize, &[6, 6, 6, 6, 1, 1]);
+        assert!(player.got_upper_bonus);
+        assert_eq!(player.score, 86 + 100);
+    }
+
+    #[test]
+    fn house_rules_values() {
+        let house_rules = HouseRules {
+            full_house: Some(30), large_straight: Some(35), yahtzee_bonus: Some(false),
+            upper_threshold: Some(10), upper_bonus: Some(20), ..Default::default()
+        };
+        let mut player = PlayerData::with_house_rules(Rules::Yahtzee, house_rules);
+        let scores = calculate_scores(&[2, 2, 3, 3, 3], &player);
+        assert_eq!(scores[Combinations::FullHouse as usize], 30);
+        assert_eq!(calculate_scores(&[1, 2, 3, 4, 5], &player)[Combinations::LargeStraight as usize], 35);
+        assert_eq!(calculate_scores(&[1, 2, 3, 4, 6], &player)[Combinations::LargeStraight as usize], 0);
+
+        player.add_score(Combinations::Yahtzee as usize, &[4; 5]);
+        // A Joker scores the house value, but there's no bonus
+        player.add_score(Combinations::FullHouse as usize, &[5; 5]);
+        assert_eq!(player.combinations_scores[Combinations::FullHouse as usize], 30);
+        assert_eq!(player.yahtzee_bonuses, 0);
+        player.add_score(Combinations::Threes as usize, &[3, 3, 3, 3, 1]);
+        assert!(player.got_upper_bonus);
+        assert_eq!(player.score, 50 + 30 + 12 + 20);
+    }
+
+    #[test]
+    fn worthless_yahtzees_still_earn_bonuses() {
+        let house_rules = HouseRules { yahtzee: Some(0), ..Default::default() };
+        let mut player = PlayerData::with_house_rules(Rules::Yahtzee, house_rules.clone());
+        player.add_score(Combinations::Yahtzee as usize, &[4; 5]);
+        assert_eq!(player.combinations_scores[Combinations::Yahtzee as usize], 0);
+        assert!(player.has_scored_yahtzee(0));
+        player.add_score(Combinations::Fives as usize, &[5; 5]);
+        assert_eq!(player.yahtzee_bonuses, 1);
+        assert_eq!(player.score, 25 + 100);
+
+        // Crossing the box out is still no Yahtzee
+        let mut player = PlayerData::with_house_rules(Rules::Yahtzee, house_rules);
+        player.add_score(Combinations::Yahtzee as usize, &[1, 2, 3, 4, 6]);
+        assert!(!player.has_scored_yahtzee(0));
+        player.add_score(Combinations::Fives as usize, &[5; 5]);
+        assert_eq!(player.yahtzee_bonuses, 0);
+    }
+}
@@ -0,0 +1,203 @@
+//! Games between AIs without any UI, played on every core to compare
+//! strategies over many games.
+//!
+//! Game `i` rolls the dice of the seed `seed + i`, and random AIs play it with
+//! generators of their own seeded by it, so a simulation gives the same
+//! results with any number of threads.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::ai::{play_turn, Difficulty, RandomAi, Strategy};
+use crate::game::{GameState, Player, PlayerKind};
+use crate::house_rules::HouseRules;
+use crate::rules::Rules;
+
+/// How one of the AIs did over all games
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AiResults {
+    /// Games with the highest score
+    pub wins: u64,
+    /// Games with the highest score, shared with another AI
+    pub ties: u64,
+    pub score_total: i64,
+    score_squares: i64,
+    /// Columns that earned the upper bonus
+    pub upper_bonuses: u64,
+    pub yahtzee_bonuses: u64,
+    /// How many times each box of the scorecard scored each number of points
+    pub boxes: Vec<BTreeMap<u8, u64>>,
+}
+
+impl AiResults {
+    fn new(rules: Rules) -> Self {
+        AiResults {
+            wins: 0,
+            ties: 0,
+            score_total: 0,
+            score_squares: 0,
+            upper_bonuses: 0,
+            yahtzee_bonuses: 0,
+            boxes: vec![BTreeMap::new(); rules.scorecard_len()],
+        }
+    }
+
+    fn add(&mut self, other: AiResults) {
+        self.wins += other.wins;
+        self.ties += other.ties;
+        self.score_total += other.score_total;
+        self.score_squares += other.score_squares;
+        self.upper_bonuses += other.upper_bonuses;
+        self.yahtzee_bonuses += other.yahtzee_bonuses;
+        for (counts, other_counts) in self.boxes.iter_mut().zip(other.boxes) {
+            for (points, n) in other_counts {
+                *counts.entry(points).or_default() += n;
+            }
+        }
+    }
+
+    /// Number of games these results are from
+    pub fn games(&self) -> u64 {
+        self.boxes.first().map_or(0, |counts| counts.values().sum())
+    }
+
+    pub fn mean(&self) -> f64 {
+        match self.games() {
+            0 => 0.0,
+            games => self.score_total as f64 / games as f64,
+        }
+    }
+
+    /// Standard deviation of the final scores
+    pub fn std_dev(&self) -> f64 {
+        match self.games() {
+            0 => 0.0,
+            games => (self.score_squares as f64 / games as f64 - self.mean().powi(2)).max(0.0).sqrt(),
+        }
+    }
+
+    /// Average points of the box at `index`
+    pub fn box_mean(&self, index: usize) -> f64 {
+        let counts = &self.boxes[index];
+        let points: u64 = counts.iter().map(|(&points, &n)| points as u64 * n).sum();
+        points as f64 / self.games().max(1) as f64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Simulation {
+    pub rules: Rules,
+    pub games: u64,
+    pub seed: u64,
+    /// In the order of the strategies
+    pub ais: Vec<AiResults>,
+}
+
+/// An AI of the simulation. The random AI changes as it plays, so each game
+/// gets new ones, the others are shared by all the threads.
+enum Ai {
+    Random,
+    Shared(Box<dyn Strategy + Send + Sync>),
+}
+
+/// Plays `games` games between the AIs, one seat each, on `threads` threads.
+/// The optimal AI is loaded from `strategy_cache`, see [`Difficulty::strategy`].
+pub fn simulate(rules: Rules, house_rules: &HouseRules, difficulties: &[Difficulty], strategy_cache: &Path,
+                games: u64, seed: u64, threads: usize) -> Simulation {
+    let strategies: Vec<Ai> = difficulties.iter()
+        .map(|difficulty| match difficulty {
+            Difficulty::Random => Ai::Random,
+            _ => Ai::Shared(difficulty.strategy(rules, house_rules, strategy_cache, seed)),
+        })
+        .collect();
+    let strategies = &strategies;
+    let threads = threads.clamp(1, games.max(1) as usize) as u64;
+    let results: Vec<Vec<AiResults>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|thread| scope.spawn(move || {
+                let mut ais: Vec<AiResults> = strategies.iter().map(|_| AiResults::new(rules)).collect();
+                for game in (thread..games).step_by(threads as usize) {
+                    play_game(rules, house_rules, strategies, seed.wrapping_add(game), &mut ais);
+                }
+                ais
+            }))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let mut ais: Vec<AiResults> = difficulties.iter().map(|_| AiResults::new(rules)).collect();
+    for thread_ais in results {
+        for (ai, thread_ai) in ais.iter_mut().zip(thread_ais) {
+            ai.add(thread_ai);
+        }
+    }
+    Simulation { rules, games, seed, ais }
+}
+
+fn play_game(rules: Rules, house_rules: &HouseRules, ais_of_game: &[Ai], seed: u64, ais: &mut [AiResults]) {
+    // Seeded apart from the dice and from each other
+    let randoms: Vec<Option<RandomAi>> = ais_of_game.iter().enumerate()
+        .map(|(i, ai)| matches!(ai, Ai::Random).then(|| RandomAi::new(seed.wrapping_add((i as u64 + 1) << 32))))
+        .collect();
+    let strategies: Vec<&dyn Strategy> = ais_of_game.iter().zip(&randoms)
+        .map(|(ai, random)| match ai {
+            Ai::Shared(strategy) => strategy.as_ref() as &dyn Strategy,
+            Ai::Random => random.as_ref().expect("made for every random AI"),
+        })
+        .collect();
+
+    let players = (1..=strategies.len()).map(|i| Player::new(&format!("AI {}", i), PlayerKind::AI)).collect();
+    let mut game_state = GameState::with_house_rules(players, rules, house_rules.clone(), seed);
+    while !game_state.is_over() {
+        play_turn(strategies[game_state.turn.player], &mut game_state);
+    }
+
+    let best = game_state.players.iter().map(|p| p.data.score).max().expect("a game has players");
+    let winners = game_state.players.iter().filter(|p| p.data.score == best).count();
+    for (ai, player) in ais.iter_mut().zip(&game_state.players) {
+        let data = &player.data;
+        match (data.score == best, winners) {
+            (true, 1) => ai.wins += 1,
+            (true, _) => ai.ties += 1,
+            _ => (),
+        }
+        ai.score_total += data.score as i64;
+        ai.score_squares += data.score as i64 * data.score as i64;
+        ai.upper_bonuses += (0..rules.columns()).filter(|&column| data.has_upper_bonus(column)).count() as u64;
+        ai.yahtzee_bonuses += data.yahtzee_bonuses as u64;
+        for (counts, &points) in ai.boxes.iter_mut().zip(&data.combinations_scores) {
+            *counts.entry(points).or_default() += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn threads_dont_change_results() {
+        let ais = [Difficulty::Greedy, Difficulty::Heuristic];
+        // Only the optimal AI would need it
+        let cache = Path::new("unused");
+        let one = simulate(Rules::Yahtzee, &HouseRules::default(), &ais, cache, 40, 7, 1);
+        let four = simulate(Rules::Yahtzee, &HouseRules::default(), &ais, cache, 40, 7, 4);
+        assert_eq!(one, four);
+
+        let [greedy, heuristic] = &one.ais[..] else { panic!("two AIs play") };
+        assert_eq!(greedy.games(), 40);
+        assert_eq!(greedy.wins + heuristic.wins + greedy.ties.max(heuristic.ties), 40);
+        assert!(heuristic.mean() > greedy.mean());
+        assert!(heuristic.std_dev() > 0.0);
+        let box_means: f64 = (0..13).map(|i| heuristic.box_mean(i)).sum();
+        let bonuses = (35 * heuristic.upper_bonuses + 100 * heuristic.yahtzee_bonuses) as f64 / 40.0;
+        assert!((box_means + bonuses - heuristic.mean()).abs() < 1e-9);
+
+        // Random AIs don't play differently on other threads, nor like each other
+        let randoms = [Difficulty::Random, Difficulty::Random, Difficulty::Greedy];
+        let one = simulate(Rules::Yahtzee, &HouseRules::default(), &randoms, cache, 20, 7, 1);
+        let three = simulate(Rules::Yahtzee, &HouseRules::default(), &randoms, cache, 20, 7, 3);
+        assert_eq!(one, three);
+        assert_ne!(one.ais[0], one.ais[1]);
+    }
+}
@@ -0,0 +1,603 @@
+//! Optimal strategy for a solitaire game of Yahtzee, or of any other [`Rules`].
+//!
+//! The state between turns is the set of used combinations, the upper section
+//! sum (capped at 63, which is all the bonus cares about) and whether Yahtzee
+//! was scored for 50 (which makes later Yahtzees worth a 100 point bonus where
+//! the rules have Jokers).
+//! [`Solver`] stores the expected final score still to be gained from every
+//! such state under optimal play, computed backwards from the full scorecard.
+//! Decisions inside a turn are made by expanding a single turn on top of it.
+//!
+//! Scorecards with several columns are played by the solution of a single
+//! column: a choice is worth its weighted gain in its column on top of the
+//! weighted expected scores of all columns. That's a good strategy rather
+//! than the optimal one, which would need far too many states.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+
+use crate::files::write_atomically;
+use crate::rules::Rules;
+use crate::scoring::{calculate_scores, Combinations, PlayerData};
+
+const FACES: usize = 6;
+const DICE: usize = 5;
+const UPPER_BONUS_THRESHOLD: usize = 63;
+const UPPER_SUMS: usize = UPPER_BONUS_THRESHOLD + 1;
+
+/// Number of states of a scorecard with `categories` combinations
+fn states(categories: usize) -> usize {
+    (1 << categories) * UPPER_SUMS * 2
+}
+
+fn cache_magic(rules: Rules) -> &'static [u8; 8] {
+    match rules.column_rules() {
+        Rules::Yatzy => b"YTZYSOL1",
+        _ => b"YTZSOLV1",
+    }
+}
+
+/// Dice multisets are identified by their counts of each face
+type Counts = [u8; FACES];
+
+fn counts_code(counts: &Counts) -> usize {
+    counts.iter().rev().fold(0, |code, &c| code * FACES + c as usize)
+}
+
+fn multisets(size: usize) -> Vec<Counts> {
+    fn go(face: usize, left: usize, counts: &mut Counts, out: &mut Vec<Counts>) {
+        if face == FACES - 1 {
+            counts[face] = left as u8;
+            out.push(*counts);
+            return;
+        }
+        for c in (0..=left).rev() {
+            counts[face] = c as u8;
+            go(face + 1, left - c, counts, out);
+        }
+    }
+    let mut out = Vec::new();
+    go(0, size, &mut [0; FACES], &mut out);
+    out
+}
+
+fn factorial(n: usize) -> f32 {
+    (1..=n).product::<usize>() as f32
+}
+
+/// Probability of rolling exactly these counts with `counts.sum()` dice
+fn multiset_probability(counts: &Counts) -> f32 {
+    let n = counts.iter().map(|&c| c as usize).sum::<usize>();
+    let permutations = counts.iter().fold(factorial(n), |p, &c| p / factorial(c as usize));
+    permutations / (FACES as f32).powi(n as i32)
+}
+
+fn counts_to_dice(counts: &Counts) -> Vec<u8> {
+    (0..FACES).flat_map(|f| std::iter::repeat_n(f as u8 + 1, counts[f] as usize)).collect()
+}
+
+fn dice_to_counts(dice: &[u8]) -> Counts {
+    let mut counts = [0; FACES];
+    for &d in dice {
+        counts[d as usize - 1] += 1;
+    }
+    counts
+}
+
+/// Everything about dice that doesn't depend on the scorecard
+struct Tables {
+    /// All 252 distinct rolls of five dice
+    rolls: Vec<Counts>,
+    roll_index: Vec<u16>,
+    roll_scores: Vec<Vec<u8>>,
+    /// Face of the roll if it is a Yahtzee
+    roll_yahtzee: Vec<Option<usize>>,
+    /// All 462 multisets of 0 to 5 dice that can be kept
+    keeps: Vec<Counts>,
+    keep_index: Vec<u16>,
+    /// Rolls reachable by rerolling the rest of the dice, with their probabilities
+    keep_outcomes: Vec<Vec<(u16, f32)>>,
+    /// Keeps that can be made from each roll
+    roll_keeps: Vec<Vec<u16>>,
+}
+
+impl Tables {
+    fn new(rules: Rules) -> Self {
+        let rolls = multisets(DICE);
+        let mut roll_index = vec![u16::MAX; FACES.pow(FACES as u32)];
+        for (i, r) in rolls.iter().enumerate() {
+            roll_index[counts_code(r)] = i as u16;
+        }
+        let roll_scores = rolls.iter()
+            .map(|r| calculate_scores(&counts_to_dice(r), &PlayerData::new(rules)))
+            .collect();
+        let roll_yahtzee = rolls.iter()
+            .map(|r| r.iter().position(|&c| c as usize == DICE))
+            .collect();
+
+        let keeps: Vec<Counts> = (0..=DICE).flat_map(multisets).collect();
+        let mut keep_index = vec![u16::MAX; FACES.pow(FACES as u32)];
+        for (i, k) in keeps.iter().enumerate() {
+            keep_index[counts_code(k)] = i as u16;
+        }
+        let keep_outcomes = keeps.iter().map(|k| {
+            let kept = k.iter().map(|&c| c as usize).sum::<usize>();
+            multisets(DICE - kept).iter().map(|rolled| {
+                let mut outcome = *k;
+                for f in 0..FACES {
+                    outcome[f] += rolled[f];
+                }
+                (roll_index[counts_code(&outcome)], multiset_probability(rolled))
+            }).collect()
+        }).collect();
+        let roll_keeps = rolls.iter().map(|r| {
+            let mut subsets = vec![[0u8; FACES]];
+            for f in 0..FACES {
+                subsets = subsets.iter()
+                    .flat_map(|s| (0..=r[f]).map(move |c| {
+                        let mut s = *s;
+                        s[f] = c;
+                        s
+                    }))
+                    .collect();
+            }
+            subsets.iter().map(|s| keep_index[counts_code(s)]).collect()
+        }).collect();
+
+        Tables { rolls, roll_index, roll_scores, roll_yahtzee, keeps, keep_index, keep_outcomes, roll_keeps }
+    }
+}
+
+/// Scorecard state between turns, as far as the future is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct State {
+    used: u16,
+    upper: usize,
+    yahtzee_scored: bool,
+}
+
+impl State {
+    fn of(player: &PlayerData, column: usize) -> Self {
+        let categories = player.rules.combinations().len();
+        let start = column * categories;
+        let used = (0..categories)
+            .filter(|&i| player.has_used(start + i))
+            .fold(0, |used, i| used | 1 << i);
+        State {
+            used,
+            upper: std::cmp::min(player.upper_sum(column) as usize, UPPER_BONUS_THRESHOLD),
+            yahtzee_scored: player.rules.has_jokers() && player.has_scored_yahtzee(column),
+        }
+    }
+    fn index(&self) -> usize {
+        (self.used as usize * UPPER_SUMS + self.upper) * 2 + self.yahtzee_scored as usize
+    }
+    fn has_used(&self, i: usize) -> bool {
+        self.used & 1 << i != 0
+    }
+}
+
+/// The best play for a roll, see [`Solver::hint`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hint {
+    /// The dice to hold for the next reroll and the expected final score
+    /// after it, if there are rerolls left
+    pub hold: Option<(Vec<bool>, f32)>,
+    /// The expected final score after scoring each combination now,
+    /// `None` for the ones that can't be used
+    pub combinations: Vec<Option<f32>>,
+}
+
+/// The state of every column of a scorecard with the weight of the column
+type Columns = Vec<(f32, State)>;
+
+fn columns(player: &PlayerData) -> Columns {
+    (0..player.rules.columns())
+        .map(|column| (player.rules.column_weight(column) as f32, State::of(player, column)))
+        .collect()
+}
+
+pub struct Solver {
+    /// The rules of a single column
+    rules: Rules,
+    tables: Tables,
+    /// Expected score still to be gained from each state
+    values: Vec<f32>,
+}
+
+impl Solver {
+    /// Solves the whole game. This takes a while, see [`Solver::load_or_compute`].
+    /// Panics for rules without a solver, see [`Rules::has_solver`].
+    pub fn new(rules: Rules) -> Self {
+        Self::compute(rules, 0)
+    }
+
+    /// Only solves states with at least `min_used` combinations used
+    pub(crate) fn compute(rules: Rules, min_used: u32) -> Self {
+        assert!(rules.has_solver(), "{} has no solver", rules.title());
+        let rules = rules.column_rules();
+        let categories = rules.combinations().len();
+        let mut solver = Solver { rules, tables: Tables::new(rules), values: vec![0.0; states(categories)] };
+        let yahtzee = rules.index(Combinations::Yahtzee).expect("every ruleset has a Yahtzee");
+
+        // Upper sums that can actually occur for each set of used upper combinations
+        let mut upper_reachable = [[false; UPPER_SUMS]; 1 << 6];
+        for (mask, reachable) in upper_reachable.iter_mut().enumerate() {
+            reachable[0] = true;
+            for face in (0..6).filter(|f| mask & 1 << f != 0) {
+                let before = *reachable;
+                *reachable = [false; UPPER_SUMS];
+                for sum in (0..UPPER_SUMS).filter(|&s| before[s]) {
+                    for count in 0..=DICE {
+                        reachable[std::cmp::min(sum + count * (face + 1), UPPER_BONUS_THRESHOLD)] = true;
+                    }
+                }
+            }
+        }
+
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        for used_count in (min_used..categories as u32).rev() {
+            let states: Vec<State> = (0..1u16 << categories)
+                .filter(|used| used.count_ones() == used_count)
+                .flat_map(|used| {
+                    let reachable = upper_reachable[used as usize & 0b111111];
+                    let yahtzee_used = rules.has_jokers() && used & 1 << yahtzee != 0;
+                    (0..UPPER_SUMS)
+                        .filter(move |&upper| reachable[upper])
+                        .flat_map(move |upper| {
+                            let flags: &[bool] = if yahtzee_used { &[false, true] } else { &[false] };
+                            flags.iter().map(move |&yahtzee_scored| State { used, upper, yahtzee_scored })
+                        })
+                })
+                .collect();
+
+            let chunk_size = states.len().div_ceil(threads).max(1);
+            let results: Vec<Vec<(usize, f32)>> = std::thread::scope(|scope| {
+                let solver = &solver;
+                let handles: Vec<_> = states.chunks(chunk_size)
+                    .map(|chunk| scope.spawn(move || {
+                        chunk.iter().map(|s| (s.index(), solver.turn_value(s))).collect()
+                    }))
+                    .collect();
+                handles.into_iter().map(|h| h.join().unwrap()).collect()
+            });
+            for (index, value) in results.into_iter().flatten() {
+                solver.values[index] = value;
+            }
+        }
+        solver
+    }
+
+    /// Loads the solution from the cache file, solving and saving it if it's not there
+    pub fn load_or_compute(path: &Path, rules: Rules) -> Self {
+        if let Ok(solver) = Self::load(path, rules) {
+            return solver;
+        }
+        let solver = Self::new(rules);
+        // Not being able to cache only makes the next start slower
+        let _ = solver.save(path);
+        solver
+    }
+
+    /// Loads a solution for `rules` saved by [`Solver::save`]
+    pub fn load(path: &Path, rules: Rules) -> io::Result<Self> {
+        let rules = rules.column_rules();
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != cache_magic(rules) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("not a {} strategy file", rules)));
+        }
+        let states = states(rules.combinations().len());
+        let mut bytes = Vec::with_capacity(states * 4);
+        reader.read_to_end(&mut bytes)?;
+        if bytes.len() != states * 4 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated strategy file"));
+        }
+        let values = bytes.chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        Ok(Solver { rules, tables: Tables::new(rules), values })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_atomically(path, |writer| {
+            writer.write_all(cache_magic(self.rules))?;
+            for value in &self.values {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            Ok(())
+        })
+    }
+
+    /// The rules of a single column the solver plays
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+
+    /// Expected final score still to be gained by a player at the start of a turn
+    pub fn expected_score(&self, player: &PlayerData) -> f32 {
+        columns(player).iter().map(|(weight, state)| weight * self.values[state.index()]).sum()
+    }
+
+    /// Value of scoring each box with the final roll: the points
+    /// (including bonuses) plus the expected score of the rest of the game.
+    /// Boxes that can't be used are `None`.
+    pub fn combination_values(&self, player: &PlayerData, dice: &[u8]) -> Vec<Option<f32>> {
+        let roll = self.roll_of(dice);
+        let mut values = vec![None; player.rules.scorecard_len()];
+        self.for_each_box(&columns(player), roll, |i, value| values[i] = Some(value));
+        values
+    }
+
+    pub fn best_combination(&self, player: &PlayerData, dice: &[u8]) -> usize {
+        self.combination_values(player, dice).iter()
+            .enumerate()
+            .filter_map(|(i, v)| v.map(|v| (i, v)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("player must have at least one combination to choose")
+            .0
+    }
+
+    /// Expected value of holding the given dice and rerolling the rest,
+    /// with `rolls_left` rerolls (including this one) left in the turn
+    pub fn hold_value(&self, player: &PlayerData, dice: &[u8], held: &[bool], rolls_left: u32) -> f32 {
+        let kept: Vec<u8> = dice.iter().zip(held).filter(|(_, &h)| h).map(|(&d, _)| d).collect();
+        let keep = self.tables.keep_index[counts_code(&dice_to_counts(&kept))] as usize;
+        self.keep_values(&columns(player), rolls_left)[keep]
+    }
+
+    /// The hold maximizing the expected score with `rolls_left` rerolls left.
+    /// Holding all dice means the roll should be scored as it is.
+    pub fn best_hold(&self, player: &PlayerData, dice: &[u8], rolls_left: u32) -> Vec<bool> {
+        let keep_values = self.keep_values(&columns(player), rolls_left);
+        let roll = self.roll_of(dice);
+        let best = self.tables.roll_keeps[roll].iter()
+            .map(|&k| k as usize)
+            .max_by(|&a, &b| {
+                // Prefer keeping more dice on ties so that a finished roll isn't rerolled
+                keep_values[a].total_cmp(&keep_values[b])
+                    .then(self.keep_size(a).cmp(&self.keep_size(b)))
+            })
+            .unwrap();
+
+        let mut to_keep = self.tables.keeps[best];
+        let mut held = vec![false; dice.len()];
+        for (i, &d) in dice.iter().enumerate() {
+            if to_keep[d as usize - 1] > 0 {
+                to_keep[d as usize - 1] -= 1;
+                held[i] = true;
+            }
+        }
+        held
+    }
+
+    /// What the optimal strategy would do with the roll, in expected final scores
+    pub fn hint(&self, player: &PlayerData, dice: &[u8], rolls_left: u32) -> Hint {
+        let score = player.score as f32;
+        let hold = (rolls_left > 0).then(|| {
+            let held = self.best_hold(player, dice, rolls_left);
+            let value = score + self.hold_value(player, dice, &held, rolls_left);
+            (held, value)
+        });
+        let combinations = self.combination_values(player, dice).into_iter().map(|value| value.map(|v| score + v)).collect();
+        Hint { hold, combinations }
+    }
+
+    fn keep_size(&self, keep: usize) -> u8 {
+        self.tables.keeps[keep].iter().sum()
+    }
+
+    fn roll_of(&self, dice: &[u8]) -> usize {
+        self.tables.roll_index[counts_code(&dice_to_counts(dice))] as usize
+    }
+
+    /// Calls `f` with every box of the scorecard allowed for the roll and the value of choosing it
+    fn for_each_box(&self, columns: &[(f32, State)], roll: usize, mut f: impl FnMut(usize, f32)) {
+        let total: f32 = columns.iter().map(|(weight, state)| weight * self.values[state.index()]).sum();
+        let categories = self.rules.combinations().len();
+        for (column, (weight, state)) in columns.iter().enumerate() {
+            let others = total - weight * self.values[state.index()];
+            self.for_each_choice(state, *weight, roll, |c, value| f(column * categories + c, others + value));
+        }
+    }
+
+    /// Calls `f` with every combination of the column allowed for the roll and
+    /// the value of choosing it, the column counting `weight` times
+    fn for_each_choice(&self, state: &State, weight: f32, roll: usize, mut f: impl FnMut(usize, f32)) {
+        let scores = &self.tables.roll_scores[roll];
+        let categories = scores.len();
+        let yahtzee = self.rules.index(Combinations::Yahtzee).expect("every ruleset has a Yahtzee");
+        let joker_face = self.tables.roll_yahtzee[roll].filter(|_| self.rules.has_jokers() && state.has_used(yahtzee));
+
+        let lower = Combinations::Sixes as usize + 1..categories;
+        let allowed = |c: usize| {
+            if state.has_used(c) {
+                return false;
+            }
+            match joker_face {
+                None => true,
+                Some(face) if !state.has_used(face) => c == face,
+                Some(_) if lower.clone().any(|l| !state.has_used(l)) => lower.contains(&c),
+                Some(_) => true,
+            }
+        };
+
+        for c in (0..categories).filter(|&c| allowed(c)) {
+            let mut score = scores[c] as usize;
+            // Only Yahtzee has Jokers, so its indices are the combinations
+            if joker_face.is_some() {
+                score = match c {
+                    c if c == Combinations::FullHouse as usize => 25,
+                    c if c == Combinations::SmallStraight as usize => 30,
+                    c if c == Combinations::LargeStraight as usize => 40,
+                    _ => score,
+                };
+            }
+            let mut gain = score;
+            // Extra Yahtzee bonuses aren't multiplied
+            let yahtzee_bonus = if joker_face.is_some() && state.yahtzee_scored { 100.0 } else { 0.0 };
+            let mut upper = state.upper;
+            if c <= Combinations::Sixes as usize {
+                upper = std::cmp::min(upper + score, UPPER_BONUS_THRESHOLD);
+                if state.upper < UPPER_BONUS_THRESHOLD && upper == UPPER_BONUS_THRESHOLD {
+                    gain += self.rules.upper_bonus() as usize;
+                }
+            }
+            let next = State {
+                used: state.used | 1 << c,
+                upper,
+                yahtzee_scored: state.yahtzee_scored || (self.rules.has_jokers() && c == yahtzee && score > 0),
+            };
+            f(c, weight * (gain as f32 + self.values[next.index()]) + yahtzee_bonus);
+        }
+    }
+
+    /// Value of each final roll when it's scored optimally
+    fn final_roll_values(&self, columns: &[(f32, State)]) -> Vec<f32> {
+        (0..self.tables.rolls.len()).map(|roll| {
+            let mut best = f32::NEG_INFINITY;
+            self.for_each_box(columns, roll, |_, value| best = best.max(value));
+            best
+        }).collect()
+    }
+
+    fn expected_keep_values(&self, roll_values: &[f32]) -> Vec<f32> {
+        self.tables.keep_outcomes.iter()
+            .map(|outcomes| outcomes.iter().map(|&(r, p)| p * roll_values[r as usize]).sum())
+            .collect()
+    }
+
+    fn best_roll_values(&self, keep_values: &[f32]) -> Vec<f32> {
+        self.tables.roll_keeps.iter()
+            .map(|keeps| keeps.iter().map(|&k| keep_values[k as usize]).fold(f32::NEG_INFINITY, f32::max))
+            .collect()
+    }
+
+    /// Value of every keep when `rolls_left` rerolls are left
+    fn keep_values(&self, columns: &[(f32, State)], rolls_left: u32) -> Vec<f32> {
+        let mut roll_values = self.final_roll_values(columns);
+        let mut keep_values = self.expected_keep_values(&roll_values);
+        for _ in 1..rolls_left {
+            roll_values = self.best_roll_values(&keep_values);
+            keep_values = self.expected_keep_values(&roll_values);
+        }
+        keep_values
+    }
+
+    fn turn_value(&self, state: &State) -> f32 {
+        let keep_values = self.keep_values(&[(1.0, *state)], 3);
+        let nothing_kept = self.tables.keep_index[0] as usize;
+        keep_values[nothing_kept]
+    }
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::new(Rules::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn player_with_only(left: &[usize]) -> PlayerData {
+        let mut player = PlayerData::default();
+        for i in (0..13).filter(|i| !left.contains(i)) {
+            player.combinations_used[i] = true;
+        }
+        player
+    }
+
+    #[test]
+    fn last_turn_values() {
+        let solver = Solver::compute(Rules::Yahtzee, 12);
+
+        // Chance with optimal rerolls
+        let chance = player_with_only(&[Combinations::Chance as usize]);
+        assert!((solver.expected_score(&chance) - 23.3326).abs() < 1e-3);
+
+        // Yahtzee comes up in three rolls 4.6% of the time
+        let yahtzee = player_with_only(&[Combinations::Yahtzee as usize]);
+        assert!((solver.expected_score(&yahtzee) - 50.0 * 0.046029).abs() < 1e-3);
+    }
+
+    #[test]
+    fn holds_for_yahtzee() {
+        let solver = Solver::compute(Rules::Yahtzee, 12);
+        let player = player_with_only(&[Combinations::Yahtzee as usize]);
+        let held = solver.best_hold(&player, &[2, 5, 5, 1, 5], 2);
+        assert_eq!(held, [false, true, true, false, true]);
+        let held = solver.best_hold(&player, &[4, 4, 4, 4, 4], 1);
+        assert_eq!(held, [true; 5]);
+    }
+
+    #[test]
+    fn hint_adds_the_score_so_far() {
+        let solver = Solver::compute(Rules::Yahtzee, 12);
+        let mut player = player_with_only(&[Combinations::Yahtzee as usize]);
+        player.score = 200;
+        let hint = solver.hint(&player, &[2, 5, 5, 1, 5], 1);
+        let (held, expected) = hint.hold.unwrap();
+        assert_eq!(held, [false, true, true, false, true]);
+        assert!((expected - (200.0 + 50.0 / 36.0)).abs() < 1e-3);
+        assert_eq!(hint.combinations[Combinations::Yahtzee as usize], Some(200.0));
+        assert_eq!(hint.combinations[Combinations::Chance as usize], None);
+        assert_eq!(solver.hint(&player, &[5; 5], 0).hold, None);
+    }
+
+    #[test]
+    fn yatzy_has_no_jokers() {
+        let solver = Solver::compute(Rules::Yatzy, 13);
+        let index = |c| Rules::Yatzy.index(c).unwrap();
+        let mut player = PlayerData::new(Rules::Yatzy);
+        for i in 0..15 {
+            player.combinations_used[i] = ![index(Combinations::Yahtzee), index(Combinations::FullHouse)].contains(&i);
+        }
+        assert!(solver.expected_score(&player) > 50.0 * 0.046029);
+
+        player.add_score(index(Combinations::Yahtzee), &[3; 5]);
+        let values = solver.combination_values(&player, &[3; 5]);
+        assert_eq!(values[index(Combinations::FullHouse)], Some(0.0));
+        assert_eq!(values.iter().flatten().count(), 1);
+    }
+
+    #[test]
+    fn triple_columns_add_up() {
+        let solver = Solver::compute(Rules::TripleYahtzee, 12);
+        let chance = Combinations::Chance as usize;
+        let mut player = PlayerData::new(Rules::TripleYahtzee);
+        for i in (0..39).filter(|&i| i % 13 != chance) {
+            player.combinations_used[i] = true;
+        }
+        assert!((solver.expected_score(&player) - 6.0 * 23.3326).abs() < 1e-2);
+
+        // Chance counts the most in the last column
+        assert_eq!(solver.best_combination(&player, &[6, 6, 5, 5, 4]), 26 + chance);
+        let values = solver.combination_values(&player, &[6, 6, 5, 5, 4]);
+        assert!((values[26 + chance].unwrap() - values[chance].unwrap() - 2.0 * (26.0 - 23.3326)).abs() < 1e-2);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let solver = Solver::compute(Rules::Yahtzee, 12);
+        let path = std::env::temp_dir().join(format!("yahtzee-solver-test-{}", std::process::id()));
+        solver.save(&path).unwrap();
+        let loaded = Solver::load(&path, Rules::Yahtzee).unwrap();
+        let other_rules = Solver::load(&path, Rules::Yatzy);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(solver.values, loaded.values);
+        assert!(other_rules.is_err());
+    }
+
+    // The well known optimal expected score. Slow in debug builds:
+    // cargo test --release -- --ignored
+    #[test]
+    #[ignore]
+    fn full_game_value() {
+        let solver = Solver::new(Rules::Yahtzee);
+        assert!((solver.expected_score(&PlayerData::default()) - 254.59).abs() < 0.01);
+    }
+}
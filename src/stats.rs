@@ -0,0 +1,251 @@
+//! Lifetime statistics of the human players for each ruleset, kept as versioned JSON.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::Difficulty;
+use crate::files::{load_versioned, write_atomically};
+use crate::game::{GameState, PlayerKind};
+use crate::rules::Rules;
+use crate::scoring::{Combinations, PlayerData};
+
+/// Version of the stats file written by this version of the game
+pub const VERSION: u32 = 2;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub games: u32,
+    /// Results of the games against each AI level
+    pub results: BTreeMap<Difficulty, Record>,
+    /// Final score of every game, oldest first
+    pub scores: Vec<i32>,
+    pub upper_bonuses: u32,
+    /// Games with a Yahtzee scored in its box
+    pub yahtzees: u32,
+    /// In the scorecard order of the rules, adding up the columns
+    pub combination_totals: Vec<u32>,
+}
+
+impl PlayerStats {
+    fn add_game(&mut self, player: &PlayerData) {
+        self.games += 1;
+        self.scores.push(player.score);
+        self.upper_bonuses += player.got_upper_bonus as u32;
+        let mut yahtzee = player.rules.indices(Combinations::Yahtzee);
+        self.yahtzees += yahtzee.any(|i| player.combinations_scores[i] > 0) as u32;
+        let combinations = player.rules.combinations().len();
+        self.combination_totals.resize(combinations, 0);
+        for (i, &score) in player.combinations_scores.iter().enumerate() {
+            self.combination_totals[i % combinations] += score as u32;
+        }
+    }
+
+    fn per_game(&self, n: u32) -> f64 {
+        if self.games == 0 { 0.0 } else { n as f64 / self.games as f64 }
+    }
+
+    pub fn average_score(&self) -> f64 {
+        if self.games == 0 { 0.0 } else { self.scores.iter().map(|&s| s as f64).sum::<f64>() / self.games as f64 }
+    }
+    pub fn upper_bonus_rate(&self) -> f64 {
+        self.per_game(self.upper_bonuses)
+    }
+    pub fn yahtzee_rate(&self) -> f64 {
+        self.per_game(self.yahtzees)
+    }
+    pub fn combination_averages(&self) -> Vec<f64> {
+        self.combination_totals.iter().map(|&total| self.per_game(total)).collect()
+    }
+
+    /// Number of scores in each range of `bucket` points, from the lowest range
+    /// with a score to the highest. Ranges are given by their first score.
+    pub fn histogram(&self, bucket: i32) -> Vec<(i32, usize)> {
+        let (Some(&min), Some(&max)) = (self.scores.iter().min(), self.scores.iter().max()) else {
+            return Vec::new();
+        };
+        let first = min.div_euclid(bucket);
+        let last = max.div_euclid(bucket);
+        (first..=last)
+            .map(|b| (b * bucket, self.scores.iter().filter(|&&s| s.div_euclid(bucket) == b).count()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stats {
+    pub version: u32,
+    /// By the rules of the games, then by player name
+    #[serde(default)]
+    pub rulesets: BTreeMap<Rules, BTreeMap<String, PlayerStats>>,
+    /// Version 1 only had Yahtzee games, by player name
+    #[serde(default, skip_serializing)]
+    players: BTreeMap<String, PlayerStats>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats { version: VERSION, rulesets: BTreeMap::new(), players: BTreeMap::new() }
+    }
+}
+
+impl Stats {
+    /// Reads the stats, which are empty until the first game is recorded
+    pub fn load(path: &Path) -> io::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let mut stats = load_versioned(path, VERSION, |stats: &Stats| stats.version)?;
+        if stats.version < 2 {
+            stats.rulesets.insert(Rules::Yahtzee, std::mem::take(&mut stats.players));
+            stats.version = VERSION;
+        }
+        Ok(stats)
+    }
+
+    pub fn players(&self, rules: Rules) -> Option<&BTreeMap<String, PlayerStats>> {
+        self.rulesets.get(&rules).filter(|players| !players.is_empty())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rulesets.values().all(BTreeMap::is_empty)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_atomically(path, |writer| Ok(serde_json::to_writer(writer, self)?))
+    }
+
+    /// Adds a finished game for the humans in `seats`. Their results only
+    /// count against the AI level if there were AIs in the game.
+    pub fn record_game(&mut self, game_state: &GameState, difficulty: Difficulty, seats: &[usize]) {
+        let standings = game_state.standings();
+        let winners = standings.iter().filter(|(place, _)| *place == 1).count();
+        let against_ai = game_state.players.iter().any(|p| p.kind == PlayerKind::AI);
+        let players = self.rulesets.entry(game_state.rules()).or_default();
+
+        for &seat in seats {
+            let player = &game_state.players[seat];
+            if player.kind != PlayerKind::Human {
+                continue;
+            }
+            let stats = players.entry(player.name.clone()).or_default();
+            stats.add_game(&player.data);
+            if against_ai {
+                let place = standings.iter()
+                    .find(|(_, p)| std::ptr::eq(*p, player))
+                    .map(|(place, _)| *place)
+                    .expect("every player has a place");
+                let record = stats.results.entry(difficulty).or_default();
+                match (place, winners) {
+                    (1, 1) => record.wins += 1,
+                    (1, _) => record.ties += 1,
+                    _ => record.losses += 1,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::Player;
+
+    fn finished_game(scores: &[(&str, PlayerKind, i32)]) -> GameState {
+        let mut game_state = GameState::new(scores.iter().map(|(name, kind, _)| Player::new(name, *kind)).collect());
+        for (player, (_, _, score)) in game_state.players.iter_mut().zip(scores) {
+            player.data.score = *score;
+        }
+        game_state
+    }
+
+    #[test]
+    fn records_results_against_ai() {
+        let mut stats = Stats::default();
+        let won = finished_game(&[("Me", PlayerKind::Human, 250), ("AI", PlayerKind::AI, 200)]);
+        let tied = finished_game(&[("Me", PlayerKind::Human, 200), ("AI", PlayerKind::AI, 200)]);
+        let lost = finished_game(&[("Me", PlayerKind::Human, 150), ("AI", PlayerKind::AI, 200)]);
+        stats.record_game(&won, Difficulty::Greedy, &[0, 1]);
+        stats.record_game(&tied, Difficulty::Greedy, &[0, 1]);
+        stats.record_game(&lost, Difficulty::Optimal, &[0, 1]);
+
+        let players = stats.players(Rules::Yahtzee).unwrap();
+        let me = &players["Me"];
+        assert_eq!(players.len(), 1);
+        assert_eq!(me.games, 3);
+        assert_eq!(me.results[&Difficulty::Greedy], Record { wins: 1, losses: 0, ties: 1 });
+        assert_eq!(me.results[&Difficulty::Optimal], Record { wins: 0, losses: 1, ties: 0 });
+        assert_eq!(me.average_score(), 200.0);
+    }
+
+    #[test]
+    fn hot_seat_games_have_no_ai_results() {
+        let mut stats = Stats::default();
+        let game = finished_game(&[("A", PlayerKind::Human, 100), ("B", PlayerKind::Human, 90)]);
+        stats.record_game(&game, Difficulty::Heuristic, &[0, 1]);
+        let b = &stats.players(Rules::Yahtzee).unwrap()["B"];
+        assert_eq!(b.games, 1);
+        assert!(b.results.is_empty());
+    }
+
+    #[test]
+    fn migrates_version_1() {
+        let path = std::env::temp_dir().join(format!("yahtzee-stats-version-test-{}", std::process::id()));
+        std::fs::write(&path, r#"{"version":1,"players":{"Me":{"games":1,"results":{},"scores":[200],
+            "upper_bonuses":0,"yahtzees":0,"combination_totals":[0,0,0,0,0,0,0,0,0,0,0,0,200]}}}"#).unwrap();
+        let stats = Stats::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(stats.version, VERSION);
+        assert_eq!(stats.players(Rules::Yahtzee).unwrap()["Me"].scores, [200]);
+        assert!(stats.players(Rules::Yatzy).is_none());
+        assert!(!serde_json::to_string(&stats).unwrap().contains("\"players\""));
+    }
+
+    #[test]
+    fn yatzy_games_are_kept_apart() {
+        let mut stats = Stats::default();
+        let mut game = GameState::with_rules(vec![Player::new("Me", PlayerKind::Human)], Rules::Yatzy, 0);
+        let yatzy = Rules::Yatzy.index(Combinations::Yahtzee).unwrap();
+        game.players[0].data.add_score(yatzy, &[3; 5]);
+        stats.record_game(&game, Difficulty::Greedy, &[0]);
+
+        assert!(stats.players(Rules::Yahtzee).is_none());
+        let me = &stats.players(Rules::Yatzy).unwrap()["Me"];
+        assert_eq!(me.combination_averages().len(), 15);
+        assert_eq!(me.yahtzee_rate(), 1.0);
+        assert_eq!(me.combination_averages()[yatzy], 50.0);
+    }
+
+    #[test]
+    fn rates_and_averages() {
+        let mut stats = PlayerStats::default();
+        let mut player = PlayerData::default();
+        for (i, dice) in [[6, 6, 6, 6, 6], [5, 5, 5, 5, 5], [4, 4, 4, 4, 4]].iter().enumerate() {
+            player.add_score(5 - i, dice);
+        }
+        player.add_score(Combinations::Yahtzee as usize, &[1, 1, 1, 1, 1]);
+        stats.add_game(&player);
+        stats.add_game(&PlayerData::default());
+
+        assert_eq!(stats.upper_bonus_rate(), 0.5);
+        assert_eq!(stats.yahtzee_rate(), 0.5);
+        assert_eq!(stats.combination_averages()[Combinations::Sixes as usize], 15.0);
+    }
+
+    #[test]
+    fn histogram_buckets() {
+        let stats = PlayerStats { scores: vec![99, 100, 124, 180], ..Default::default() };
+        assert_eq!(stats.histogram(25), [(75, 1), (100, 2), (125, 0), (150, 0), (175, 1)]);
+        assert!(PlayerStats::default().histogram(25).is_empty());
+    }
+}
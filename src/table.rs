@@ -0,0 +1,129 @@
+//! What the ncurses screens and `--plain` share: the tables the players make
+//! their moves at, the boxes and dice as text, and the files a game ends up in.
+
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+
+use yahtzee::*;
+
+/// Name of the box at `index`, with its column if the scorecard has several
+pub fn box_name(rules: Rules, index: usize) -> String {
+    let name = score_index_to_string(rules, index);
+    if rules.columns() > 1 {
+        format!("{} (x{})", name, rules.column_weight(rules.column(index)))
+    } else {
+        name.to_string()
+    }
+}
+
+pub fn dice_string(dice: &[u8], held: &[bool]) -> String {
+    let strs: Vec<_> = dice.iter().zip(held)
+        .map(|(d, &h)| if h { format!("[{}]", d) } else { format!(" {} ", d) })
+        .collect();
+    strs.join(" ")
+}
+
+/// Which roll of the turn the dice are, e.g. "2/3". Saved rerolls make the
+/// number of rolls vary, so by those rules it's the rerolls left instead.
+pub fn roll_progress(game_state: &GameState) -> String {
+    let rolls = game_state.house_rules().rerolls() + 1;
+    if game_state.rules().saves_rolls() {
+        format!("{} rerolls left", game_state.rolls_left())
+    } else {
+        format!("{}/{}", rolls - game_state.rolls_left(), rolls)
+    }
+}
+
+/// Where a human plays their turn: the local game or one hosted over the network
+pub trait Table: Moves {
+    fn toggle_hold(&mut self, index: usize) -> Result<(), GameError> {
+        let held = self.game().held()[index];
+        self.hold(index, !held)
+    }
+    /// Records that the current player looked at a hint
+    fn use_hint(&mut self) -> Result<(), GameError>;
+    /// The game to save when quitting, if it can be saved here
+    fn save(&self) -> Option<SavedGame>;
+    /// Whether the turns show everyone's scorecards instead of the dice
+    fn show_scorecards(&mut self) -> &mut bool;
+}
+
+/// A game played on this terminal only
+pub struct LocalTable {
+    pub game_state: GameState,
+    pub difficulty: Difficulty,
+    pub recorder: Option<Recorder<File>>,
+    pub show_scorecards: bool,
+}
+
+impl Moves for LocalTable {
+    fn game(&self) -> &GameState {
+        &self.game_state
+    }
+    fn hold(&mut self, index: usize, held: bool) -> Result<(), GameError> {
+        self.game_state.hold(index, held);
+        Ok(())
+    }
+    fn reroll(&mut self) -> Result<(), GameError> {
+        match &mut self.recorder {
+            Some(recorder) => recorder.reroll(&mut self.game_state),
+            None => self.game_state.reroll(),
+        }
+    }
+    fn score(&mut self, index: usize) -> Result<u8, GameError> {
+        match &mut self.recorder {
+            Some(recorder) => recorder.score(&mut self.game_state, index),
+            None => self.game_state.score(index),
+        }
+    }
+}
+
+impl Table for LocalTable {
+    fn use_hint(&mut self) -> Result<(), GameError> {
+        match &mut self.recorder {
+            Some(recorder) => recorder.hint(&mut self.game_state),
+            None => self.game_state.use_hint(),
+        }
+        Ok(())
+    }
+    fn save(&self) -> Option<SavedGame> {
+        Some(SavedGame { difficulty: self.difficulty, game_state: self.game_state.clone() })
+    }
+    fn show_scorecards(&mut self) -> &mut bool {
+        &mut self.show_scorecards
+    }
+}
+
+/// A new replay file for a game starting now
+pub fn replay_path(paths: &Paths) -> PathBuf {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    paths.replays().join(format!("{}.jsonl", now.as_secs()))
+}
+
+/// Adds the game to the stats of the humans in `local_seats`
+pub fn record_stats(paths: &Paths, game_state: &GameState, difficulty: Difficulty, local_seats: &[usize]) -> io::Result<()> {
+    let path = paths.stats();
+    let mut stats = Stats::load(&path)?;
+    stats.record_game(game_state, difficulty, local_seats);
+    stats.save(&path)
+}
+
+/// The highscores the humans' scores of a finished game go into, and whether
+/// the best of those scores beats them all
+pub fn load_highscores(paths: &Paths, game_state: &GameState, humans: &[&Player]) -> io::Result<(Highscores, bool)> {
+    let highscores = Highscores::load_or_migrate(&paths.highscores(), paths.old_highscores().as_deref())?;
+    let best_human_score = humans.iter().map(|p| p.data.score).max();
+    let rules = game_state.rules();
+    let new_best = best_human_score.is_some_and(|score| highscores.best(rules).map(|x| x.score < score).unwrap_or(true));
+    Ok((highscores, new_best))
+}
+
+/// Adds the humans' scores to the highscores from [`load_highscores`] and saves them
+pub fn add_highscores(paths: &Paths, mut highscores: Highscores, game_state: &GameState, difficulty: Difficulty,
+                      humans: &[&Player]) -> io::Result<()> {
+    for human in humans {
+        highscores.add(Highscore::new(human, difficulty, game_state.seed()));
+    }
+    highscores.save(&paths.highscores())
+}
@@ -0,0 +1,339 @@
+//! Tournaments between the built-in AIs and bots, two players a game.
+//!
+//! Every pairing plays its games two by two, both games of a pair rolling the
+//! dice of the same seed with the seats swapped: game `n` of the tournament
+//! uses the seed `seed + n / 2`. A win is worth a point and a tie half of one.
+//!
+//! A round robin pairs everyone with everyone once. A Swiss tournament plays a
+//! number of rounds, each pairing players with as many points as possible who
+//! haven't met yet. With an odd number of players, the last one who hasn't sat
+//! out yet sits out the round and gets the points of winning all its games.
+//!
+//! Bots are started again for every game. A bot that breaks off a game, see
+//! [`crate::bot`], loses it.
+
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::ai::{play_turn, Difficulty, Strategy};
+use crate::bot::BotProcess;
+use crate::game::{GameState, Player, PlayerKind};
+use crate::house_rules::HouseRules;
+use crate::ratings::Ratings;
+use crate::rules::Rules;
+
+/// Who plays for a contestant
+pub enum Entrant {
+    Ai { difficulty: Difficulty, strategy: Box<dyn Strategy + Send + Sync> },
+    /// A program speaking the bot protocol
+    Bot(PathBuf),
+}
+
+pub struct Contestant {
+    pub name: String,
+    pub entrant: Entrant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    RoundRobin,
+    Swiss { rounds: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameResult {
+    /// From 1
+    pub round: usize,
+    pub seed: u64,
+    /// Indices of the contestants, by seat
+    pub contestants: [usize; 2],
+    pub scores: [i32; 2],
+    /// The seat that broke off the game and lost it, with the error
+    pub forfeit: Option<(usize, String)>,
+}
+
+impl GameResult {
+    /// 1 if the first seat won, 0.5 for a tie and 0 if the second seat won
+    pub fn result(&self) -> f64 {
+        match (&self.forfeit, self.scores[0].cmp(&self.scores[1])) {
+            (Some((seat, _)), _) => *seat as f64,
+            (None, std::cmp::Ordering::Greater) => 1.0,
+            (None, std::cmp::Ordering::Equal) => 0.5,
+            (None, std::cmp::Ordering::Less) => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Standing {
+    pub games: u32,
+    pub wins: u32,
+    pub ties: u32,
+    pub losses: u32,
+    /// Rounds sat out in a Swiss tournament
+    pub byes: u32,
+    pub points: f64,
+    pub score_total: i64,
+}
+
+impl Standing {
+    pub fn mean_score(&self) -> f64 {
+        if self.games == 0 { 0.0 } else { self.score_total as f64 / self.games as f64 }
+    }
+}
+
+pub struct Tournament {
+    pub rules: Rules,
+    pub house_rules: HouseRules,
+    pub format: Format,
+    /// Games of every pairing, an even number
+    pub games: u32,
+    pub seed: u64,
+    pub contestants: Vec<Contestant>,
+    /// By contestant
+    pub standings: Vec<Standing>,
+    pub results: Vec<GameResult>,
+}
+
+impl Tournament {
+    /// A tournament that has yet to be played. `games` is rounded up to an even number.
+    pub fn new(rules: Rules, house_rules: HouseRules, format: Format, games: u32, seed: u64,
+               contestants: Vec<Contestant>) -> Self {
+        let standings = vec![Standing::default(); contestants.len()];
+        let games = games.max(1).next_multiple_of(2);
+        Tournament { rules, house_rules, format, games, seed, contestants, standings, results: Vec::new() }
+    }
+
+    /// Plays every round, calling `on_game` after each game
+    pub fn run(&mut self, mut on_game: impl FnMut(&Tournament, &GameResult)) {
+        let mut played = HashSet::new();
+        let rounds = match self.format {
+            Format::RoundRobin => round_robin(self.contestants.len()),
+            Format::Swiss { rounds } => vec![Vec::new(); rounds],
+        };
+        for (round, pairs) in rounds.into_iter().enumerate() {
+            let pairs = match self.format {
+                Format::RoundRobin => pairs,
+                Format::Swiss { .. } => self.swiss_pairs(&played),
+            };
+            for (a, b) in pairs {
+                played.insert((a.min(b), a.max(b)));
+                for game in 0..self.games {
+                    let seed = self.seed.wrapping_add(self.results.len() as u64 / 2);
+                    let seats = if game % 2 == 0 { [a, b] } else { [b, a] };
+                    let result = self.play_game(round + 1, seats, seed);
+                    self.add_result(result);
+                    on_game(self, self.results.last().expect("the game was added"));
+                }
+            }
+        }
+    }
+
+    /// Indices of the contestants from the first place to the last one
+    pub fn ranking(&self) -> Vec<usize> {
+        let mut ranking: Vec<usize> = (0..self.contestants.len()).collect();
+        ranking.sort_by(|&a, &b| {
+            let (a, b) = (&self.standings[a], &self.standings[b]);
+            b.points.total_cmp(&a.points).then(b.mean_score().total_cmp(&a.mean_score()))
+        });
+        ranking
+    }
+
+    /// Pairs the contestants by points for the next round of a Swiss
+    /// tournament, giving a bye first if there's an odd number of them
+    fn swiss_pairs(&mut self, played: &HashSet<(usize, usize)>) -> Vec<(usize, usize)> {
+        let mut unpaired = self.ranking();
+        if unpaired.len() % 2 == 1 {
+            let fewest_byes = unpaired.iter().map(|&c| self.standings[c].byes).min().expect("there are contestants");
+            let bye = unpaired.iter().rposition(|&c| self.standings[c].byes == fewest_byes).expect("someone has the fewest");
+            let standing = &mut self.standings[unpaired.remove(bye)];
+            standing.byes += 1;
+            standing.points += self.games as f64;
+        }
+        let mut pairs = Vec::new();
+        while !unpaired.is_empty() {
+            let a = unpaired.remove(0);
+            let b = unpaired.iter().position(|&b| !played.contains(&(a.min(b), a.max(b)))).unwrap_or(0);
+            pairs.push((a, unpaired.remove(b)));
+        }
+        pairs
+    }
+
+    fn play_game(&self, round: usize, seats: [usize; 2], seed: u64) -> GameResult {
+        let players = seats.iter().map(|&c| Player::new(&self.contestants[c].name, PlayerKind::AI)).collect();
+        let mut game_state = GameState::with_house_rules(players, self.rules, self.house_rules.clone(), seed);
+        let mut result = GameResult { round, seed, contestants: seats, scores: [0; 2], forfeit: None };
+
+        let mut bots: Vec<Option<BotProcess>> = Vec::new();
+        for (seat, &c) in seats.iter().enumerate() {
+            let bot = match &self.contestants[c].entrant {
+                Entrant::Bot(path) => Some(BotProcess::spawn(&mut Command::new(path)).and_then(|mut bot| {
+                    let opponent = &self.contestants[seats[1 - seat]].entrant;
+                    let difficulty = match opponent {
+                        Entrant::Ai { difficulty, .. } => *difficulty,
+                        Entrant::Bot(_) => Difficulty::Heuristic,
+                    };
+                    bot.welcome(seat, difficulty).map(|_| bot)
+                })),
+                Entrant::Ai { .. } => None,
+            };
+            match bot.transpose() {
+                Ok(bot) => bots.push(bot),
+                Err(e) => {
+                    result.forfeit = Some((seat, e.to_string()));
+                    return result;
+                }
+            }
+        }
+
+        while !game_state.is_over() {
+            let seat = game_state.turn.player;
+            match (&self.contestants[seats[seat]].entrant, &mut bots[seat]) {
+                (_, Some(bot)) => {
+                    if let Err(e) = bot.play_turn(&mut game_state) {
+                        result.forfeit = Some((seat, e.to_string()));
+                        break;
+                    }
+                }
+                (Entrant::Ai { strategy, .. }, None) => {
+                    play_turn(strategy.as_ref(), &mut game_state);
+                }
+                (Entrant::Bot(_), None) => unreachable!("bots are started for the game"),
+            }
+        }
+        for bot in bots.iter_mut().flatten() {
+            // The game is decided, whether the bot hears about it or not
+            let _ = bot.game_over(&game_state);
+        }
+        result.scores = [game_state.players[0].data.score, game_state.players[1].data.score];
+        result
+    }
+
+    fn add_result(&mut self, result: GameResult) {
+        let first = result.result();
+        for (seat, &c) in result.contestants.iter().enumerate() {
+            let points = if seat == 0 { first } else { 1.0 - first };
+            let standing = &mut self.standings[c];
+            standing.games += 1;
+            standing.points += points;
+            standing.score_total += result.scores[seat] as i64;
+            match points {
+                1.0 => standing.wins += 1,
+                0.5 => standing.ties += 1,
+                _ => standing.losses += 1,
+            }
+        }
+        self.results.push(result);
+    }
+
+    /// Writes the standings as CSV, one line per contestant from the first place
+    pub fn write_csv(&self, ratings: &Ratings, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "place,name,games,wins,ties,losses,byes,points,mean_score,rating")?;
+        for (place, &c) in self.ranking().iter().enumerate() {
+            let (contestant, standing) = (&self.contestants[c], &self.standings[c]);
+            writeln!(writer, "{},{},{},{},{},{},{},{},{:.2},{:.1}", place + 1, csv_field(&contestant.name),
+                     standing.games, standing.wins, standing.ties, standing.losses, standing.byes, standing.points,
+                     standing.mean_score(), ratings.get(self.rules, &contestant.name).rating)?;
+        }
+        Ok(())
+    }
+}
+
+/// Rounds of a round robin between `n` contestants, by the circle method
+fn round_robin(n: usize) -> Vec<Vec<(usize, usize)>> {
+    // With an odd number of contestants, whoever meets `n` sits the round out
+    let mut circle: Vec<usize> = (0..n + n % 2).collect();
+    let len = circle.len();
+    let mut rounds = Vec::new();
+    for _ in 1..len {
+        let pairs = (0..len / 2)
+            .map(|i| (circle[i], circle[len - 1 - i]))
+            .filter(|&(a, b)| a < n && b < n)
+            .collect();
+        rounds.push(pairs);
+        circle[1..].rotate_right(1);
+    }
+    rounds
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ai::{GreedyAi, HeuristicAi, RandomAi};
+
+    fn ais() -> Vec<Contestant> {
+        let entrants: [(Difficulty, Box<dyn Strategy + Send + Sync>); 3] = [
+            (Difficulty::Random, Box::new(RandomAi::new(1))),
+            (Difficulty::Greedy, Box::new(GreedyAi)),
+            (Difficulty::Heuristic, Box::new(HeuristicAi)),
+        ];
+        entrants.into_iter()
+            .map(|(difficulty, strategy)| Contestant { name: difficulty.name().to_string(), entrant: Entrant::Ai { difficulty, strategy } })
+            .collect()
+    }
+
+    #[test]
+    fn round_robin_meets_everyone_once() {
+        for n in 2..8 {
+            let rounds = round_robin(n);
+            let pairs: HashSet<(usize, usize)> = rounds.iter().flatten().map(|&(a, b)| (a.min(b), a.max(b))).collect();
+            assert_eq!(pairs.len(), n * (n - 1) / 2);
+            assert_eq!(rounds.iter().map(Vec::len).sum::<usize>(), pairs.len());
+        }
+    }
+
+    #[test]
+    fn plays_a_round_robin() {
+        let mut tournament = Tournament::new(Rules::Yahtzee, HouseRules::default(), Format::RoundRobin, 1, 4, ais());
+        let mut games = 0;
+        tournament.run(|_, _| games += 1);
+
+        assert_eq!((tournament.games, games), (2, 6));
+        assert!(tournament.standings.iter().all(|s| s.games == 4 && s.wins + s.ties + s.losses == 4));
+        let points: f64 = tournament.standings.iter().map(|s| s.points).sum();
+        assert_eq!(points, 6.0);
+        assert_eq!(tournament.contestants[tournament.ranking()[0]].name, "heuristic");
+        // Both games of a pairing roll the same seed
+        assert_eq!(tournament.results[0].seed, tournament.results[1].seed);
+        assert_eq!(tournament.results[0].contestants, [tournament.results[1].contestants[1], tournament.results[1].contestants[0]]);
+    }
+
+    #[test]
+    fn swiss_gives_byes_and_avoids_rematches() {
+        let mut tournament = Tournament::new(Rules::Yahtzee, HouseRules::default(), Format::Swiss { rounds: 3 }, 2, 1, ais());
+        tournament.run(|_, _| ());
+        assert!(tournament.standings.iter().all(|s| s.byes == 1 && s.games == 4));
+        let pairs: HashSet<[usize; 2]> = tournament.results.iter().map(|r| {
+            let [a, b] = r.contestants;
+            [a.min(b), a.max(b)]
+        }).collect();
+        assert_eq!(pairs.len(), 3);
+    }
+
+    #[test]
+    fn missing_bots_forfeit() {
+        let mut contestants = ais();
+        contestants.truncate(1);
+        contestants.push(Contestant { name: "nothing".to_string(), entrant: Entrant::Bot("/nonexistent/bot".into()) });
+        let mut tournament = Tournament::new(Rules::Yahtzee, HouseRules::default(), Format::RoundRobin, 2, 1, contestants);
+        tournament.run(|_, _| ());
+        assert_eq!(tournament.standings[0].wins, 2);
+        assert!(tournament.results.iter().all(|r| r.forfeit.is_some()));
+
+        let mut csv = Vec::new();
+        tournament.contestants[1].name = "a, \"b\"".to_string();
+        tournament.write_csv(&Ratings::default(), &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().nth(2), Some("2,\"a, \"\"b\"\"\",2,0,0,2,0,0,0.00,1500.0"));
+    }
+}
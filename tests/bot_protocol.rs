@@ -0,0 +1,87 @@
+//! The reference bot and the game's `--bot-protocol` mode, each run as a subprocess.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+use yahtzee::net::{ClientMessage, ServerMessage};
+use yahtzee::*;
+
+fn reference_bot() -> BotProcess {
+    BotProcess::spawn(&mut Command::new(env!("CARGO_BIN_EXE_yahtzee-bot"))).unwrap()
+}
+
+#[test]
+fn reference_bot_plays_a_game() {
+    let players = vec![Player::new("Bot", PlayerKind::AI), Player::new("AI", PlayerKind::AI)];
+    let mut game_state = GameState::with_seed(players, 1);
+    let mut bots = [(0, reference_bot())];
+    bot::play_game(&mut game_state, &mut bots, Difficulty::Greedy, &GreedyAi).unwrap();
+
+    assert!(game_state.is_over());
+    assert!(game_state.players.iter().all(|p| p.data.is_complete()));
+    assert!(game_state.players[0].data.score > 0);
+}
+
+#[test]
+fn bots_play_against_each_other() {
+    let players = ["A", "B", "C"].iter().map(|name| Player::new(name, PlayerKind::AI)).collect();
+    let mut game_state = GameState::with_rules(players, Rules::MaxiYatzy, 2);
+    let mut bots = [(0, reference_bot()), (2, reference_bot())];
+    bot::play_game(&mut game_state, &mut bots, Difficulty::Heuristic, &HeuristicAi).unwrap();
+    assert!(game_state.players.iter().all(|p| p.data.is_complete()));
+}
+
+#[test]
+fn game_speaks_the_bot_protocol() {
+    let data_dir = std::env::temp_dir().join(format!("yahtzee-bot-protocol-{}", std::process::id()));
+    let mut game = Command::new(env!("CARGO_BIN_EXE_yahtzee"))
+        .arg("--data-dir").arg(&data_dir)
+        .args(["--bot-protocol", "--opponents", "2", "--ai", "greedy", "--seed", "5"])
+        .env("XDG_CONFIG_HOME", &data_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut to_game = game.stdin.take().unwrap();
+    let from_game = BufReader::new(game.stdout.take().unwrap());
+
+    // Scores every roll right away in the first free box
+    let mut turns = 0;
+    let mut seat = None;
+    let mut last_state = None;
+    for line in from_game.lines() {
+        match serde_json::from_str(&line.unwrap()).unwrap() {
+            ServerMessage::Welcome { seat: s, difficulty } => {
+                assert_eq!(difficulty, Difficulty::Greedy);
+                seat = Some(s);
+            }
+            ServerMessage::State(view) if !view.over => {
+                assert_eq!(Some(view.turn.player), seat);
+                assert_eq!(view.players.len(), 3);
+                turns += 1;
+                let score = ClientMessage::Score { combination: view.available[0] };
+                writeln!(to_game, "{}", serde_json::to_string(&score).unwrap()).unwrap();
+            }
+            ServerMessage::State(view) => last_state = Some(view),
+            message => panic!("unexpected {:?}", message),
+        }
+    }
+    assert!(game.wait().unwrap().success());
+    let _ = std::fs::remove_dir_all(&data_dir);
+
+    assert_eq!(turns, 13);
+    let last_state = last_state.unwrap();
+    assert_eq!(last_state.seed, Some(5));
+    assert!(last_state.players.iter().all(|p| p.data.is_complete()));
+}
+
+#[test]
+fn bots_play_tournaments() {
+    let contestants = vec![
+        Contestant { name: "bot".to_string(), entrant: Entrant::Bot(env!("CARGO_BIN_EXE_yahtzee-bot").into()) },
+        Contestant { name: "random".to_string(), entrant: Entrant::Ai { difficulty: Difficulty::Random, strategy: Box::new(RandomAi::new(1)) } },
+    ];
+    let mut tournament = Tournament::new(Rules::Yatzy, HouseRules::default(), Format::RoundRobin, 2, 7, contestants);
+    tournament.run(|_, result| assert_eq!(result.forfeit, None));
+    assert_eq!(tournament.standings[0].wins, 2);
+}
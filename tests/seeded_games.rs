@@ -0,0 +1,70 @@
+//! Full games are reproducible from their seed.
+
+use yahtzee::*;
+
+fn players() -> Vec<Player> {
+    ["A", "B", "C"].iter().map(|name| Player::new(name, PlayerKind::AI)).collect()
+}
+
+fn play(game_state: &mut GameState, strategy: &dyn Strategy) -> Vec<usize> {
+    let mut choices = Vec::new();
+    while !game_state.is_over() {
+        choices.push(play_turn(strategy, game_state));
+    }
+    choices
+}
+
+fn scorecards(game_state: &GameState) -> Vec<(i32, Vec<u8>)> {
+    game_state.players.iter().map(|p| (p.data.score, p.data.combinations_scores.clone())).collect()
+}
+
+#[test]
+fn same_seed_same_game() {
+    for seed in [0, 1, 20261017, u64::MAX] {
+        let mut a = GameState::with_seed(players(), seed);
+        let mut b = GameState::with_seed(players(), seed);
+        assert_eq!(play(&mut a, &HeuristicAi), play(&mut b, &HeuristicAi));
+        assert_eq!(scorecards(&a), scorecards(&b));
+        assert_eq!(a.seed(), seed);
+    }
+}
+
+#[test]
+fn random_ai_follows_its_seed() {
+    let mut a = GameState::with_seed(players(), 7);
+    let mut b = GameState::with_seed(players(), 7);
+    assert_eq!(play(&mut a, &RandomAi::new(7)), play(&mut b, &RandomAi::new(7)));
+    assert_eq!(scorecards(&a), scorecards(&b));
+}
+
+#[test]
+fn different_seeds_differ() {
+    let first_rolls: Vec<Vec<u8>> = (0..10).map(|seed| GameState::with_seed(players(), seed).dice().to_vec()).collect();
+    assert!(first_rolls.iter().any(|dice| *dice != first_rolls[0]));
+}
+
+/// Shared seeds must roll the same dice in every build
+#[test]
+fn dice_are_stable() {
+    let mut game_state = GameState::with_seed(players(), 42);
+    let mut dice = vec![game_state.dice().to_vec()];
+    game_state.reroll().unwrap();
+    dice.push(game_state.dice().to_vec());
+    game_state.score(12).unwrap();
+    dice.push(game_state.dice().to_vec());
+    assert_eq!(dice, [[2, 5, 1, 6, 5], [3, 3, 4, 5, 2], [4, 1, 2, 2, 1]]);
+}
+
+#[test]
+fn saved_game_continues_the_same() {
+    let mut game_state = GameState::with_seed(players(), 2024);
+    for _ in 0..10 {
+        play_turn(&GreedyAi, &mut game_state);
+    }
+    let saved = SavedGame { difficulty: Difficulty::Greedy, game_state: game_state.clone() };
+    let mut loaded: SavedGame = serde_json::from_str(&serde_json::to_string(&saved).unwrap()).unwrap();
+
+    play(&mut game_state, &GreedyAi);
+    play(&mut loaded.game_state, &GreedyAi);
+    assert_eq!(scorecards(&game_state), scorecards(&loaded.game_state));
+}